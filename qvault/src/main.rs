@@ -1,41 +1,123 @@
+use clap::Parser;
+
+mod qvault_cli;
 mod qvault_cmd;
 mod qvault_tui;
 mod qvault_log;
 mod qvault_history;
+mod qvault_config;
+mod qvault_script;
+mod qvault_markdown;
+mod qvault_theme;
+mod qvault_keymap;
+mod qvault_task;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = qvault_cli::Cli::parse();
 
     qvault_log::init_log();
-    let qh = qvault_history::QvaultHistory::new()?;
+
+    // One-shot subcommands print to stdout and never enter the full-screen UI
+    if let Some(command) = cli.command {
+        let code = qvault_cli::run(command);
+        log::logger().flush(); // shutdown_log() would print to stdout
+        std::process::exit(code);
+    }
+
+    let qh = qvault_history::QvaultHistory::new(qvault_history::DB_PATH)?;
+
+    // `git diff | qvault` keeps the piped text for the first /ai, keys come from the tty
+    let mut pipe_note = None;
+    match qvault_cmd::qvault_ai::read_piped_stdin(Some(qvault_cmd::qvault_ai::PIPE_WAIT_MS)) {
+        Ok(Some(ctx)) => {
+            qvault_log::log_info("Piped input attached, bytes: ", format_args!("{}", ctx.len()));
+            qvault_cmd::qvault_ai::set_pending_context(ctx);
+        }
+        Ok(None) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::TimedOut => {
+            qvault_log::log_warn(format!("Piped input ignored: {}", err));
+            pipe_note = Some(format!("Piped input ignored, {}; use qvault ai to wait for it", err));
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    let mut config = qvault_config::QvaultConfig::load_or_default(qvault_config::CONFIG_PATH);
+    let registry = qvault_cmd::CommandRegistry::with_builtins();
 
     // Set up terminal
     let mut qtui = qvault_tui::QvaultTerminal::new()?;
 
+    // Error from the last input, shown in place of the usual bar message
+    let mut error_msg: Option<String> = None;
+    if let Some(err) = config.load_error() {
+        error_msg = Some(format!("Config not loaded, using defaults: {}", err));
+    }
+    match qvault_theme::Theme::from_config(&config) {
+        Ok(theme) => qtui.set_theme(theme),
+        Err(err) => {
+            qvault_log::log_error(format!("Ignoring theme: {}", err));
+            error_msg = Some(err);
+        }
+    }
+    qtui.set_opener(config.pager.opener.clone());
+    match qvault_keymap::Keymap::from_config(&config) {
+        Ok(keymap) => qtui.set_keymap(keymap),
+        Err(err) => {
+            qvault_log::log_error(format!("Ignoring key bindings: {}", err));
+            error_msg = Some(err);
+        }
+    }
+
     qtui.show_qvault_screen()?;
+    // Note from the last command, e.g. "Saved alias /gh"
+    let mut status_msg: Option<String> = pipe_note;
 
     // Main event loop
     loop {
         qvault_log::log_info("Looping ..", format_args!(""));
-        let _ = qtui.write_bar_message("Enter search query below");
+        if let Some(msg) = error_msg.take() {
+            let _ = qtui.write_bar_error(&msg);
+        } else if let Some(msg) = status_msg.take() {
+            let _ = qtui.write_bar_message(&msg);
+        } else if qvault_cmd::qvault_ai::has_pending_context() {
+            let _ = qtui.write_bar_message("Piped input attached, ask about it with /ai");
+        } else {
+            let _ = qtui.write_bar_message("Enter search query below");
+        }
         qtui.show_prompt()?;
-        let iput = qtui.tui_get_input()?;
-        qvault_log::log_info("Got input from User >>", format_args!("{}", iput));
-        //let qcmd =
-        match qvault_cmd::QvaultCmd::from_input(&iput){
-            Ok(qcmd) => {
-                qcmd.clone().log_it();
-                qcmd.handle_cmd(&mut qtui);
-                qh.add_command(&qcmd.to_string());
+        // Rebuilt each time so a new /alias completes right away
+        let completions = registry.completions(&config);
+        let iput = qtui.tui_get_input(&completions)?;
+        qvault_log::log_query("Got input from User >>", &iput);
+
+        // Bare "exit" has always quit, keep it from becoming a search
+        if iput.trim() == "exit" {
+            break;
+        }
+
+        match qvault_cmd::QvaultCmd::parse_pipeline(&iput, &registry, &config) {
+            Ok(stages) => {
+                for qcmd in &stages {
+                    qcmd.log_it();
+                    let _ = qh.add_command(&qcmd.to_string());
+                }
+                match registry.run_pipeline(&stages, Some(&mut qtui), &mut config) {
+                    Ok(qvault_cmd::CmdOutput::Exit) => break,
+                    Ok(output) => status_msg = qvault_cmd::render(output, &mut qtui, &config),
+                    Err(e) => {
+                        qvault_log::log_warn(format!("Command {} failed: {}", iput.trim(), e));
+                        error_msg = Some(e.to_string());
+                    }
+                }
             }
             Err(e) => {
-                qvault_log::log_info("Error parsing user input: ", format_args!("{}", iput));
+                qvault_log::log_query("Error parsing user input: ", &iput);
+                if !iput.trim().is_empty() {
+                    error_msg = Some(e);
+                }
                 continue;
             }
         };
-
-        if iput == "exit" {
-            break;
-        }
     }
 
     qvault_log::log_info("Exiting from qvault session", format_args!(""));
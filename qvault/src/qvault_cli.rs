@@ -0,0 +1,480 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::qvault_cmd::qvault_ai::{self, PipedContext, RequestError};
+use crate::qvault_cmd::qvault_bang::{self, BangTarget};
+use crate::qvault_cmd::qvault_search::{self, SearchItem, SearchResult};
+use crate::qvault_cmd::CommandRegistry;
+use crate::qvault_config::{self, QvaultConfig};
+use crate::qvault_history::{self, QvaultHistory};
+use crate::qvault_log;
+use crate::qvault_script::{self, ReportFormat};
+use crate::qvault_task;
+
+/// Exit codes for non-interactive runs
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NO_RESULTS: i32 = 3;
+
+/// QueryVault: search and AI lookup from the terminal.
+/// Without a subcommand qvault starts the full-screen UI.
+#[derive(Parser, Debug)]
+#[command(name = "qvault", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Search using Brave Search
+    Search {
+        /// Query text, several words are joined with spaces
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Maximum number of results (up to 20)
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=20))]
+        count: Option<u8>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// AI lookup using OpenAI. Piped stdin is attached as context.
+    Ai {
+        /// Prompt text, several words are joined with spaces
+        prompt: Vec<String>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Run qvault commands from a file, one per line, and save a report
+    Run {
+        /// Script file, `#` starts a comment line
+        script: String,
+        /// Report file, `<script>.report.md` by default. `.json` selects
+        /// JSON, `-` prints the report to stdout.
+        #[arg(long, short)]
+        report: Option<String>,
+        /// JSON report instead of markdown
+        #[arg(long)]
+        json: bool,
+    },
+    /// Command history
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryAction {
+    /// Print recorded commands
+    List {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Delete all recorded commands
+    Clear,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct OutputArgs {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+    format: OutputFormat,
+    /// Shorthand for --format json
+    #[arg(long, conflicts_with = "format")]
+    json: bool,
+}
+
+impl OutputArgs {
+    fn format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            self.format
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Plain,
+    Json,
+    Markdown,
+}
+
+/// Run one subcommand and return the process exit code
+pub fn run(command: CliCommand) -> i32 {
+    match command {
+        CliCommand::Search { query, count, output } => run_search(&query.join(" "), count.map(u32::from), output.format()),
+        CliCommand::Ai { prompt, output } => run_ai(&prompt.join(" "), output.format()),
+        CliCommand::Run { script, report, json } => run_script(&script, report.as_deref(), json),
+        CliCommand::History { action } => run_history(action),
+    }
+}
+
+fn run_script(script: &str, report_path: Option<&str>, json: bool) -> i32 {
+    let registry = CommandRegistry::with_builtins();
+    let mut config = QvaultConfig::load_or_default(qvault_config::CONFIG_PATH);
+    let report = match qvault_script::run_script(script, &registry, &mut config) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("qvault: {}", err);
+            return EXIT_FAILURE;
+        }
+    };
+
+    // Next to the script like /source, unless `--report -` asks for stdout
+    let report_path = match report_path {
+        Some("-") => None,
+        Some(path) => Some(path.to_string()),
+        None if json => Some(Path::new(&qvault_script::default_report_path(script)).with_extension("json").to_string_lossy().into_owned()),
+        None => Some(qvault_script::default_report_path(script)),
+    };
+    let format = match (json, &report_path) {
+        (true, _) => ReportFormat::Json,
+        (false, Some(path)) => ReportFormat::from_path(path),
+        (false, None) => ReportFormat::Markdown,
+    };
+    match &report_path {
+        Some(path) => {
+            if let Err(err) = fs::write(path, report.render(format)) {
+                eprintln!("qvault: cannot write {}: {}", path, err);
+                return EXIT_FAILURE;
+            }
+            eprintln!("qvault: report saved to {}", path);
+        }
+        None => println!("{}", report.render(format)),
+    }
+    eprintln!("qvault: {}", report.summary());
+
+    if report.failures() > 0 {
+        EXIT_FAILURE
+    } else {
+        EXIT_OK
+    }
+}
+
+fn run_search(query: &str, count: Option<u32>, format: OutputFormat) -> i32 {
+    let config = QvaultConfig::load_or_default(qvault_config::CONFIG_PATH);
+    let search = |query: &str, count| qvault_task::block_on(qvault_search::search_brave(query.to_string(), count));
+    search_outcome(query, count, format, &config, search).emit()
+}
+
+/// Everything a subcommand prints, kept apart from printing so it can be tested
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    stdout: Option<String>,
+    stderr: Option<String>,
+    code: i32,
+}
+
+impl Outcome {
+    fn print(stdout: String, code: i32) -> Self {
+        Outcome { stdout: Some(stdout), stderr: None, code }
+    }
+
+    fn fail(code: i32, error: String) -> Self {
+        Outcome { stdout: None, stderr: Some(format!("qvault: {}", error)), code }
+    }
+
+    fn emit(self) -> i32 {
+        if let Some(out) = self.stdout.filter(|out| !out.is_empty()) {
+            println!("{}", out);
+        }
+        if let Some(err) = self.stderr {
+            eprintln!("{}", err);
+        }
+        self.code
+    }
+}
+
+/// Output of `qvault search`; `search` does the request
+fn search_outcome(
+    query: &str,
+    count: Option<u32>,
+    format: OutputFormat,
+    config: &QvaultConfig,
+    search: impl FnOnce(&str, Option<u32>) -> Result<SearchResult, String>,
+) -> Outcome {
+    if query.trim().is_empty() {
+        return Outcome::fail(EXIT_USAGE, "search query cannot be empty".to_string());
+    }
+
+    // A URL bang has nothing to search, print where it leads
+    let resolved = qvault_bang::resolve(query, &qvault_bang::bang_table(&config.bangs));
+    let query = match resolved {
+        Some(BangTarget::Url(url)) => {
+            let out = match format {
+                OutputFormat::Plain => url,
+                OutputFormat::Json => json!({ "query": query, "url": url }).to_string(),
+                OutputFormat::Markdown => format!("[{}]({})", query, url),
+            };
+            return Outcome::print(out, EXIT_OK);
+        }
+        Some(BangTarget::Search(ref q)) => q.as_str(),
+        None => query,
+    };
+
+    let result = match search(query, count) {
+        Ok(result) => result,
+        Err(err) => return Outcome::fail(EXIT_FAILURE, format!("search failed: {}", err)),
+    };
+    if let Some(err) = result.error() {
+        return Outcome::fail(EXIT_FAILURE, format!("search failed: {}", err));
+    }
+
+    let items = result.items();
+    let code = if items.is_empty() { EXIT_NO_RESULTS } else { EXIT_OK };
+    Outcome::print(format_results(query, items, format), code)
+}
+
+/// Search results as plain text, JSON or a markdown list
+fn format_results(query: &str, items: &[SearchItem], format: OutputFormat) -> String {
+    let mut lines = Vec::new();
+    match format {
+        OutputFormat::Plain => {
+            for (i, item) in items.iter().enumerate() {
+                lines.push(format!("{}. {}", i + 1, item.title()));
+                lines.push(format!("   {}", item.url()));
+                if !item.description().is_empty() {
+                    lines.push(format!("   {}", item.description()));
+                }
+            }
+        }
+        OutputFormat::Json => {
+            lines.push(json!({ "query": query, "results": items }).to_string());
+        }
+        OutputFormat::Markdown => {
+            lines.push(format!("## {}\n", query));
+            for item in items {
+                if item.description().is_empty() {
+                    lines.push(format!("- [{}]({})", item.title(), item.url()));
+                } else {
+                    lines.push(format!("- [{}]({}) - {}", item.title(), item.url(), item.description()));
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn run_ai(prompt: &str, format: OutputFormat) -> i32 {
+    // Blocks for slow producers such as `cargo build 2>&1 | qvault ai "why"`
+    let context = match qvault_ai::read_piped_stdin(None) {
+        Ok(context) => context,
+        Err(err) => {
+            eprintln!("qvault: cannot read piped input: {}", err);
+            return EXIT_FAILURE;
+        }
+    };
+    if let Some(warning) = context.as_ref().and_then(|ctx| ctx.warning()) {
+        eprintln!("qvault: warning: {}", warning);
+    }
+
+    let ask = |prompt: &str, context| qvault_task::block_on(qvault_ai::chat_with_openai(prompt.to_string(), context));
+    ai_outcome(prompt, context, format, ask).emit()
+}
+
+/// Output of `qvault ai`; `ask` does the request
+fn ai_outcome(
+    prompt: &str,
+    context: Option<PipedContext>,
+    format: OutputFormat,
+    ask: impl FnOnce(&str, Option<PipedContext>) -> Result<String, RequestError>,
+) -> Outcome {
+    let prompt = match (prompt.trim().is_empty(), &context) {
+        (false, _) => prompt,
+        (true, Some(_)) => "Explain the piped input.",
+        (true, None) => return Outcome::fail(EXIT_USAGE, "give a prompt or pipe some input".to_string()),
+    };
+
+    match ask(prompt, context) {
+        Ok(answer) => match format {
+            OutputFormat::Plain | OutputFormat::Markdown => Outcome::print(answer, EXIT_OK),
+            OutputFormat::Json => Outcome::print(json!({ "prompt": prompt, "answer": answer }).to_string(), EXIT_OK),
+        },
+        Err(err) => Outcome::fail(EXIT_FAILURE, format!("AI request failed: {}", err)),
+    }
+}
+
+fn run_history(action: HistoryAction) -> i32 {
+    history_at(qvault_history::DB_PATH, action)
+}
+
+/// run_history on the database at `path`
+fn history_at(path: &str, action: HistoryAction) -> i32 {
+    let history = match QvaultHistory::new(path) {
+        Ok(history) => history,
+        Err(err) => {
+            eprintln!("qvault: cannot open history: {}", err);
+            return EXIT_FAILURE;
+        }
+    };
+
+    let result = match action {
+        HistoryAction::List { output } => match output.format() {
+            OutputFormat::Plain => history.display_history(),
+            OutputFormat::Json => history.get_history().map(|entries| {
+                let entries: Vec<_> = entries
+                    .into_iter()
+                    .map(|(id, cmd)| json!({ "id": id, "command": cmd }))
+                    .collect();
+                println!("{}", serde_json::Value::from(entries));
+            }),
+            OutputFormat::Markdown => history.get_history().map(|entries| {
+                for (id, cmd) in entries {
+                    println!("{}. `{}`", id, cmd);
+                }
+            }),
+        },
+        HistoryAction::Clear => history.clear_history(),
+    };
+
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(err) => {
+            qvault_log::log_error(format!("History command failed: {}", err));
+            eprintln!("qvault: history command failed: {}", err);
+            EXIT_FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Fresh path in the temp dir, unique to the test and the process
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("qvault_cli_{}_{}", std::process::id(), name))
+    }
+
+    fn two_results() -> SearchResult {
+        SearchResult::from_items(&[
+            ("Tokio select", "https://docs.rs/tokio", "Waits on multiple branches"),
+            ("Async book", "https://rust-lang.github.io/async-book", ""),
+        ])
+    }
+
+    #[test]
+    fn test_search_output_and_exit_codes() {
+        let config = QvaultConfig::default();
+        let found = |_: &str, _| Ok(two_results());
+
+        let plain = search_outcome("tokio select", None, OutputFormat::Plain, &config, found);
+        assert_eq!(plain.code, EXIT_OK);
+        assert_eq!(
+            plain.stdout.unwrap(),
+            "1. Tokio select\n   https://docs.rs/tokio\n   Waits on multiple branches\n\
+             2. Async book\n   https://rust-lang.github.io/async-book"
+        );
+
+        let json = search_outcome("tokio select", None, OutputFormat::Json, &config, found);
+        let value: serde_json::Value = serde_json::from_str(&json.stdout.unwrap()).unwrap();
+        assert_eq!(value["query"], "tokio select");
+        assert_eq!(value["results"][1]["url"], "https://rust-lang.github.io/async-book");
+
+        let markdown = search_outcome("tokio select", None, OutputFormat::Markdown, &config, found);
+        assert!(markdown.stdout.unwrap().ends_with("- [Async book](https://rust-lang.github.io/async-book)"));
+
+        let none = search_outcome("zzqx", None, OutputFormat::Plain, &config, |_, _| Ok(SearchResult::from_items(&[])));
+        assert_eq!(none.code, EXIT_NO_RESULTS);
+
+        let empty = search_outcome("  ", None, OutputFormat::Plain, &config, |_, _| unreachable!());
+        assert_eq!((empty.code, empty.stderr.as_deref()), (EXIT_USAGE, Some("qvault: search query cannot be empty")));
+
+        let failed = search_outcome("tokio", Some(3), OutputFormat::Plain, &config, |_, count| {
+            assert_eq!(count, Some(3));
+            Err("HTTP Error: 429".to_string())
+        });
+        assert_eq!((failed.code, failed.stderr.as_deref()), (EXIT_FAILURE, Some("qvault: search failed: HTTP Error: 429")));
+
+        // A URL bang prints the link without searching
+        let bang = search_outcome("!w ripgrep", None, OutputFormat::Json, &config, |_, _| unreachable!());
+        assert_eq!(bang.code, EXIT_OK);
+        assert!(bang.stdout.unwrap().contains("\"url\":\"https://en.wikipedia.org/wiki/Special:Search?search=ripgrep"));
+    }
+
+    #[test]
+    fn test_ai_output_and_exit_codes() {
+        let echo = |prompt: &str, _| Ok(format!("answer to {}", prompt));
+        let plain = ai_outcome("why", None, OutputFormat::Plain, echo);
+        assert_eq!(plain, Outcome::print("answer to why".to_string(), EXIT_OK));
+
+        let json = ai_outcome("why", None, OutputFormat::Json, echo);
+        let value: serde_json::Value = serde_json::from_str(&json.stdout.unwrap()).unwrap();
+        assert_eq!((value["prompt"].as_str(), value["answer"].as_str()), (Some("why"), Some("answer to why")));
+
+        // Piped input alone gets a default prompt
+        let context = PipedContext::new("diff --git", qvault_ai::MAX_CONTEXT_BYTES);
+        let piped = ai_outcome(" ", Some(context), OutputFormat::Plain, |prompt, context| {
+            assert_eq!(context.unwrap().text(), "diff --git");
+            Ok(prompt.to_string())
+        });
+        assert_eq!(piped.stdout.as_deref(), Some("Explain the piped input."));
+
+        assert_eq!(ai_outcome("", None, OutputFormat::Plain, echo).code, EXIT_USAGE);
+        let failed = ai_outcome("why", None, OutputFormat::Plain, |_, _| Err("API key not found".into()));
+        assert_eq!(failed, Outcome::fail(EXIT_FAILURE, "AI request failed: API key not found".to_string()));
+    }
+
+    #[test]
+    fn test_bad_args_exit_with_usage() {
+        let usage_errors = [
+            &["qvault", "search"][..],
+            &["qvault", "search", "x", "--json", "--format", "plain"],
+            &["qvault", "search", "x", "--count", "0"],
+            &["qvault", "search", "x", "--count", "500"],
+            &["qvault", "nope"],
+        ];
+        for args in usage_errors {
+            let err = Cli::try_parse_from(args).unwrap_err();
+            assert_eq!(err.exit_code(), EXIT_USAGE, "{:?}", args);
+        }
+        let cli = Cli::try_parse_from(["qvault", "search", "tokio", "select", "--json"]).unwrap();
+        assert!(matches!(cli.command, Some(CliCommand::Search { ref query, ref output, .. })
+            if query.join(" ") == "tokio select" && output.format() == OutputFormat::Json));
+    }
+
+    #[test]
+    fn test_run_and_history_exit_codes() {
+        let script = temp_path("script.qv");
+        let report = temp_path("report.json");
+        fs::write(&script, "# comment\n/help\n").unwrap();
+        let run = |script: &PathBuf| run_script(script.to_str().unwrap(), report.to_str(), false);
+        assert_eq!(run(&script), EXIT_OK);
+        assert!(fs::read_to_string(&report).unwrap().contains("\"failed\":0"));
+
+        fs::write(&script, "/help\n/nosuchcommand\n").unwrap();
+        assert_eq!(run(&script), EXIT_FAILURE);
+        assert_eq!(run(&temp_path("missing.qv")), EXIT_FAILURE);
+        fs::remove_file(&report).unwrap();
+
+        // Without --report the report goes next to the script, as with /source
+        let script_path = script.to_str().unwrap();
+        let default_report = qvault_script::default_report_path(script_path);
+        assert_eq!(run_script(script_path, None, false), EXIT_FAILURE);
+        assert!(fs::read_to_string(&default_report).unwrap().contains("/nosuchcommand"));
+        fs::remove_file(&default_report).unwrap();
+        assert_eq!(run_script(script_path, Some("-"), false), EXIT_FAILURE);
+        assert!(fs::metadata(&default_report).is_err());
+        fs::remove_file(&script).unwrap();
+
+        let db = temp_path("history.db");
+        let db = db.to_str().unwrap();
+        let list = || HistoryAction::List { output: OutputArgs { format: OutputFormat::Json, json: false } };
+        assert_eq!(history_at(db, list()), EXIT_OK);
+        assert_eq!(history_at(db, HistoryAction::Clear), EXIT_OK);
+        fs::remove_dir_all(db).unwrap();
+
+        // A plain file where the database directory should be
+        let blocked = temp_path("history_file");
+        fs::write(&blocked, "").unwrap();
+        assert_eq!(history_at(blocked.to_str().unwrap(), list()), EXIT_FAILURE);
+        fs::remove_file(&blocked).unwrap();
+    }
+}
@@ -1,189 +1,457 @@
-use crate::qvault_tui::{QvaultTerminal}; // Adjust imports based on your module structure
+use crate::qvault_keymap::Action;
+use crate::qvault_tui::QvaultTerminal; // Adjust imports based on your module structure
 use crate::qvault_log;
-use serde::{Serialize, Deserialize};
+use crate::qvault_config::QvaultConfig;
+use crate::qvault_markdown;
+use crate::qvault_task;
 use std::fmt;
-use std::str::FromStr;
-use strum::EnumIter;
-use strum::IntoEnumIterator;
+use std::future::Future;
+use std::io;
+use std::process::{self, Stdio};
 use chrono::{DateTime, Utc};
 
-mod qvault_search;
-mod qvault_ai;
+pub mod qvault_search;
+pub mod qvault_ai;
+pub mod qvault_parse;
+mod qvault_alias;
+pub mod qvault_bang;
+pub mod qvault_builtin;
+mod qvault_code;
 
-#[derive(Serialize, Deserialize, Debug, Clone, EnumIter)]
-pub enum QvaultCmdName {
-    Error,
-    Help,
-    History,
+use qvault_parse::ParsedArgs;
+use qvault_search::SearchResult;
+
+/// Command used for input that does not start with `/`
+const DEFAULT_COMMAND: &str = "search";
+
+/// What a command produced. The main loop draws it with `render`, or a
+/// pipeline hands it to the next command as input.
+#[derive(Debug)]
+pub enum CmdOutput {
+    /// Nothing left to draw, the command ran its own screen (e.g. /set)
+    Nothing,
+    /// Short note for the status bar
+    Message(String),
+    /// Lines shown in a box, like /help
+    Lines { title: String, lines: Vec<String> },
+    /// Free text
+    Text { title: String, body: String },
+    /// Markdown such as an AI answer
+    Markdown { title: String, body: String },
+    /// A single link, e.g. from a URL bang
+    Link { title: String, url: String },
+    /// Search results, browsed one at a time
+    Results { query: String, result: SearchResult },
+    /// Leave qvault
     Exit,
-    Search,
-    Set,
-    Ai,
-}
-
-impl QvaultCmdName {
-    fn get_help(&self) -> String {
-        let name = match self {
-            QvaultCmdName::Help => "Show this help message",
-            QvaultCmdName::History => "History of commands",
-            QvaultCmdName::Exit => "Exit from Qvault",
-            QvaultCmdName::Search => "Search using Brave Search",
-            QvaultCmdName::Set => "Settings for Qvault",
-            QvaultCmdName::Ai => "AI lookup using OpenAI",
-            QvaultCmdName::Error => "Error(internal only)",
-        };
-        name.to_string()
+}
+
+impl CmdOutput {
+    /// Plain text handed to the next command of a pipeline, None when
+    /// there is nothing to pass on
+    pub fn to_context(&self) -> Option<String> {
+        match self {
+            CmdOutput::Nothing | CmdOutput::Exit => None,
+            CmdOutput::Message(msg) => Some(msg.clone()),
+            CmdOutput::Lines { lines, .. } => Some(lines.join("\n")),
+            CmdOutput::Text { body, .. } | CmdOutput::Markdown { body, .. } => Some(body.clone()),
+            CmdOutput::Link { title, url } => Some(format!("{}\n{}", title, url)),
+            CmdOutput::Results { query, result } => {
+                let mut text = format!("Search results for {}:\n", query);
+                for (i, item) in result.items().iter().enumerate() {
+                    text.push_str(&format!("\n{}. {}\n   {}\n", i + 1, item.title(), item.url()));
+                    if !item.description().is_empty() {
+                        text.push_str(&format!("   {}\n", item.description()));
+                    }
+                }
+                Some(text)
+            }
+        }
     }
 }
 
-impl FromStr for QvaultCmdName {
-    type Err = String;
+/// Failure reported by a command, shown in the status bar
+#[derive(Debug, Clone, PartialEq)]
+pub enum CmdError {
+    /// Arguments did not fit the command, carries the usage line
+    Usage(String),
+    /// The command ran and failed
+    Failed(String),
+}
 
-    fn from_str(c: &str) -> Result<Self, Self::Err> {
-        if !c.starts_with('/') {
-            return Ok(QvaultCmdName::Search);
+impl fmt::Display for CmdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CmdError::Usage(usage) => write!(f, "Usage: {}", usage),
+            CmdError::Failed(msg) => write!(f, "{}", msg),
         }
+    }
+}
+
+pub type CmdResult = Result<CmdOutput, CmdError>;
 
-        match c.to_lowercase().as_str() {
-            "/help" => Ok(QvaultCmdName::Help),
-            "/history" => Ok(QvaultCmdName::History),
-            "/exit" => Ok(QvaultCmdName::Exit),
-            "/search" => Ok(QvaultCmdName::Search),
-            "/set" => Ok(QvaultCmdName::Set),
-            "/ai" => Ok(QvaultCmdName::Ai),
-            _ => Ok(QvaultCmdName::Error),
+/// Arguments a command accepts, checked before it executes
+#[derive(Debug, Clone, Copy)]
+pub struct ArgSpec {
+    pub min_args: usize,
+    pub max_args: Option<usize>,
+    /// Accepted `--flag` names, without the dashes
+    pub flags: &'static [&'static str],
+    /// Skip flag checks, the command reads `QvaultCmd::rest` itself
+    pub raw: bool,
+}
+
+impl ArgSpec {
+    pub const NONE: ArgSpec = ArgSpec { min_args: 0, max_args: Some(0), flags: &[], raw: false };
+    pub const ANY: ArgSpec = ArgSpec { min_args: 0, max_args: None, flags: &[], raw: false };
+    pub const RAW: ArgSpec = ArgSpec { min_args: 0, max_args: None, flags: &[], raw: true };
+}
+
+/// Everything a command may touch while it runs
+pub struct CmdContext<'a> {
+    /// None when running a script without the full-screen UI
+    pub term: Option<&'a mut QvaultTerminal>,
+    pub registry: &'a CommandRegistry,
+    pub config: &'a mut QvaultConfig,
+    /// Output of the previous command when running in a pipeline
+    pub input: Option<CmdOutput>,
+}
+
+impl CmdContext<'_> {
+    /// The terminal, for commands that run their own screen
+    pub fn term(&mut self, command: &str) -> Result<&mut QvaultTerminal, CmdError> {
+        self.term
+            .as_deref_mut()
+            .ok_or_else(|| CmdError::Failed(format!("/{} needs the full-screen UI", command)))
+    }
+
+    /// Run a network request. In the UI it runs in the background under a
+    /// spinner and Esc cancels it; scripts just wait for it.
+    pub fn request<F>(&mut self, label: &str, task: F) -> Result<F::Output, CmdError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self.term.as_deref_mut() {
+            Some(term) => term
+                .wait_for(label, qvault_task::spawn(task))
+                .map_err(|err| CmdError::Failed(err.to_string()))?
+                .ok_or_else(|| CmdError::Failed(format!("{} cancelled", label))),
+            None => Ok(qvault_task::block_on(task)),
         }
     }
 }
 
-impl fmt::Display for QvaultCmdName {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let name = match self {
-            QvaultCmdName::Help => "Help",
-            QvaultCmdName::History => "History",
-            QvaultCmdName::Exit => "Exit",
-            QvaultCmdName::Search => "Search",
-            QvaultCmdName::Set => "Set",
-            QvaultCmdName::Ai => "AI",
-            QvaultCmdName::Error => "Error",
-        };
-        write!(f, "{}", name)
+/// A slash command. Implement this and register it with the
+/// CommandRegistry; /help, completion and dispatch pick it up from there.
+pub trait Command {
+    /// Name typed after the slash, lowercase
+    fn name(&self) -> &'static str;
+
+    /// Alternative names, without the slash
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// One line description for /help
+    fn help(&self) -> &'static str;
+
+    /// Usage line, e.g. `/search [--count=N] <query>`
+    fn usage(&self) -> &'static str;
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec::ANY
     }
+
+    /// Whether the command can sit after a `|` and use `CmdContext::input`
+    fn reads_input(&self) -> bool {
+        false
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult;
 }
 
-impl QvaultCmdName {
-    fn get_cmd(&self) -> String {
-        let name = match self {
-            QvaultCmdName::Help => "/help",
-            QvaultCmdName::History => "/history",
-            QvaultCmdName::Exit => "/exit",
-            QvaultCmdName::Search => "/search",
-            QvaultCmdName::Set => "/set",
-            QvaultCmdName::Ai => "/ai",
-            QvaultCmdName::Error => "Error",
-        };
-        name.to_string()
-    }
-}
-
-pub fn handle_search(args: &[String], term: &mut QvaultTerminal) {
-    term.clear_output_screen();
-    //term.show_msg(format!("Searching for args: {:?}", args));
-    qvault_log::log_info("Searching for args: ", format_args!("{}", args.join(", ")));
-    if !args.is_empty() {
-        match qvault_search::search_brave(&args[0]) {
-            Ok(mut result) => {
-                loop {
-                    term.clear_output_screen();
-                    term.show_output_title(result.title().to_string());
-                    term.show_output_message(1, result.snippet().to_string());
-                    term.show_output_url(result.url());
-                    term.show_output_nav(result.count());
-                    if let Ok(c) = term.navigate_search_output() {
-                        if c == 0 {
-                            break;
-                        }
-                        if c > 0 {
-                            result.next_item();
-                        } else {
-                            result.prev_item();
-                        }
+/// All known commands, in /help order
+pub struct CommandRegistry {
+    commands: Vec<Box<dyn Command>>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        CommandRegistry { commands: Vec::new() }
+    }
+
+    /// Registry with the commands that ship with qvault
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        qvault_builtin::register_all(&mut registry);
+        registry
+    }
+
+    pub fn register(&mut self, command: Box<dyn Command>) {
+        self.commands.push(command);
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &dyn Command> {
+        self.commands.iter().map(|c| c.as_ref())
+    }
+
+    /// Look up a command by name or alias, with or without the leading slash
+    pub fn find(&self, word: &str) -> Option<&dyn Command> {
+        let word = word.trim_start_matches('/').to_lowercase();
+        self.commands()
+            .find(|c| c.name() == word || c.aliases().contains(&word.as_str()))
+    }
+
+    /// Every `/name` and `/alias`, plus user aliases and macros, for
+    /// prompt completion
+    pub fn completions(&self, config: &QvaultConfig) -> Vec<String> {
+        let mut words = Vec::new();
+        for c in self.commands() {
+            words.push(format!("/{}", c.name()));
+            words.extend(c.aliases().iter().map(|a| format!("/{}", a)));
+        }
+        words.extend(config.aliases.keys().chain(config.macros.keys()).map(|a| format!("/{}", a)));
+        words
+    }
+
+    /// Command names close to what was typed, best match first
+    pub fn suggest(&self, typed: &str) -> Vec<String> {
+        let typed = typed.trim_start_matches('/').to_lowercase();
+        let mut scored: Vec<(usize, String)> = Vec::new();
+        for c in self.commands() {
+            let best = std::iter::once(c.name())
+                .chain(c.aliases().iter().copied())
+                .map(|name| {
+                    // A typed prefix of a name (`/hist`) counts as a close match
+                    if !typed.is_empty() && name.starts_with(&typed) {
+                        0
                     } else {
-                        break;
+                        edit_distance(&typed, name)
                     }
-                }
-            }
-            Err(err) => {
-                term.show_msg(format!("Search failed: {}", err));
+                })
+                .min()
+                .unwrap_or(usize::MAX);
+            if best <= 2 {
+                scored.push((best, format!("/{}", c.name())));
             }
         }
+        scored.sort();
+        scored.into_iter().map(|(_, name)| name).collect()
     }
-}
 
-pub fn handle_exit(_args: &[String], term: &mut QvaultTerminal) {
-    term.show_output_title("Quitting".to_string());
-}
+    /// Status bar message for an unknown `/command`
+    fn unknown_command_message(&self, typed: &str) -> String {
+        let suggestions = self.suggest(typed);
+        match suggestions.len() {
+            0 => format!("Unknown command {}, try /help", typed),
+            _ => format!("Unknown command {}. Did you mean {}?", typed, suggestions.join(" or ")),
+        }
+    }
+
+    /// Run the commands of a pipeline left to right, each one getting the
+    /// output of the one before. Returns what the last command produced.
+    pub fn run_pipeline(&self, stages: &[QvaultCmd], mut term: Option<&mut QvaultTerminal>, config: &mut QvaultConfig) -> CmdResult {
+        // Check every stage before running any, /set should not open its
+        // screen only for the pipeline to fail afterwards
+        for cmd in stages.iter().skip(1) {
+            let command = self
+                .find(cmd.name())
+                .ok_or_else(|| CmdError::Failed(self.unknown_command_message(cmd.name())))?;
+            if !command.reads_input() {
+                return Err(CmdError::Failed(format!("/{} does not take piped input", cmd.name())));
+            }
+        }
 
-pub fn handle_help(_args: &[String], term: &mut QvaultTerminal) {
-    let mut hstrs: Vec<String> = vec![];
-    term.clear_output_screen();
-    term.show_output_title("Help".to_string());
-    //term.tui_draw_rectangle(5,5,60,15);
-    for cmd in QvaultCmdName::iter() {
-        //term.show_msg(cmd.get_help());
-        qvault_log::log_info("CMD: ", format_args!("{} - {}", cmd.get_cmd(), cmd.get_help()));
-        hstrs.push(format!("{} - {}", cmd.get_cmd(), cmd.get_help()));
+        let mut input: Option<CmdOutput> = None;
+        let mut prev: Option<&QvaultCmd> = None;
+        for cmd in stages {
+            if let Some(prev) = prev {
+                if input.as_ref().and_then(|out| out.to_context()).is_none() {
+                    return Err(CmdError::Failed(format!("/{} has no output to pipe into /{}", prev.name(), cmd.name())));
+                }
+            }
+            let output = self.dispatch(cmd, input.take(), term.as_deref_mut(), config)?;
+            if let CmdOutput::Exit = output {
+                return Ok(output);
+            }
+            input = Some(output);
+            prev = Some(cmd);
+        }
+        Ok(input.unwrap_or(CmdOutput::Nothing))
     }
 
-    term.tui_show_help(hstrs);
-}
+    /// Check the arguments against the command's spec, then run it
+    pub fn dispatch(&self, cmd: &QvaultCmd, input: Option<CmdOutput>, term: Option<&mut QvaultTerminal>, config: &mut QvaultConfig) -> CmdResult {
+        let command = self
+            .find(cmd.name())
+            .ok_or_else(|| CmdError::Failed(self.unknown_command_message(cmd.name())))?;
+
+        let spec = command.arg_spec();
+        if spec.raw {
+            let mut ctx = CmdContext { term, registry: self, config, input };
+            return command.execute(cmd, &mut ctx);
+        }
+        for (flag, _) in &cmd.parsed.flags {
+            if !spec.flags.contains(&flag.as_str()) {
+                return Err(CmdError::Usage(format!("{}  (unknown option --{})", command.usage(), flag)));
+            }
+        }
+        let nargs = cmd.args().len();
+        if nargs < spec.min_args || spec.max_args.is_some_and(|max| nargs > max) {
+            return Err(CmdError::Usage(command.usage().to_string()));
+        }
 
-pub fn handle_history(_args: &[String], term: &mut QvaultTerminal) {
-    term.clear_output_screen();
-    term.show_output_title("History command".to_string());
+        let mut ctx = CmdContext { term, registry: self, config, input };
+        command.execute(cmd, &mut ctx)
+    }
 }
 
-pub fn handle_set(_args: &[String], term: &mut QvaultTerminal) {
-    term.clear_output_screen();
-    term.show_output_title("QVault Settings".to_string());
-    term.tui_show_settings();
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
 }
 
-pub fn handle_ai(args: &[String], term: &mut QvaultTerminal) {
-    term.clear_output_screen();
-    term.show_output_title("AI Response".to_string());
-    qvault_log::log_info("AI command executed:", format_args!("{}", args.join(", ")));
-    qvault_log::log_info("Searching for args: ", format_args!("{}", args.join(", ")));
-    if !args.is_empty() {
-        match qvault_ai::chat_with_openai(&args.join(" ")) {
-            Ok(result) => {
-                term.show_output_message(1, result.to_string());
+/// Draw a command's output. Returns a message for the status bar, if any.
+pub fn render(output: CmdOutput, term: &mut QvaultTerminal, config: &QvaultConfig) -> Option<String> {
+    match output {
+        CmdOutput::Nothing | CmdOutput::Exit => None,
+        CmdOutput::Message(msg) => Some(msg),
+        CmdOutput::Lines { title, lines } => {
+            let _ = term.clear_output_screen();
+            let _ = term.show_output_title(title);
+            term.tui_show_help(lines);
+            let _ = term.scroll_output();
+            None
+        }
+        CmdOutput::Text { title, body } => {
+            let _ = term.clear_output_screen();
+            let _ = term.show_output_title(title);
+            let _ = term.show_output_message(1, body);
+            let _ = term.scroll_output();
+            None
+        }
+        CmdOutput::Markdown { title, body } => {
+            let _ = term.clear_output_screen();
+            let _ = term.show_output_title(title);
+            let blocks = qvault_markdown::code_blocks(&body);
+            let _ = term.show_output_markdown(body);
+            let _ = term.scroll_output();
+            if blocks.is_empty() {
+                return None;
             }
-            Err(err) => {
-                term.show_msg(format!("Search failed: {}", err));
+            let list: Vec<String> = blocks
+                .iter()
+                .enumerate()
+                .map(|(i, b)| format!("[{}] {}", i + 1, if b.lang.is_empty() { "text" } else { &b.lang }))
+                .collect();
+            Some(format!("Code blocks {}  /copy N or /save N FILE", list.join(" ")))
+        }
+        CmdOutput::Link { title, url } => {
+            let _ = term.clear_output_screen();
+            let _ = term.show_output_title(title);
+            let _ = term.show_output_message(1, "Open the link below".to_string());
+            term.show_output_url(&url);
+            None
+        }
+        CmdOutput::Results { query, mut result } => {
+            // Outcome of o/y/Y, shown in the bar after the redraw
+            let mut note: Option<String> = None;
+            loop {
+                if let Err(err) = term.show_results(&query, &result) {
+                    return Some(format!("Cannot draw results: {}", err));
+                }
+                let hint = {
+                    let keys = term.keymap();
+                    format!(
+                        "{} open, {} copy URL, {} copy markdown",
+                        keys.hint(Action::Open),
+                        keys.hint(Action::CopyUrl),
+                        keys.hint(Action::CopyMarkdown)
+                    )
+                };
+                let _ = term.write_bar_message(note.as_deref().unwrap_or(&hint));
+                note = None;
+                match term.navigate_search_output(&mut result) {
+                    Ok(Action::Quit) | Err(_) => break,
+                    Ok(Action::Next) => {
+                        result.next_item();
+                    }
+                    Ok(Action::Prev) => {
+                        result.prev_item();
+                    }
+                    Ok(Action::First) => result.select(0),
+                    Ok(Action::Last) => result.select(result.count()),
+                    Ok(Action::PageDown) => result.select(result.index() + RESULTS_PAGE),
+                    Ok(Action::PageUp) => result.select(result.index().saturating_sub(RESULTS_PAGE)),
+                    Ok(Action::Open) => {
+                        note = Some(match open_url(result.url(), config.pager.opener.as_deref()) {
+                            Ok(()) => format!("Opened {}", result.url()),
+                            Err(err) => format!("Cannot open URL: {}", err),
+                        });
+                    }
+                    Ok(Action::CopyUrl) => {
+                        let url = result.url().to_string();
+                        let _ = term.copy_to_clipboard(&url);
+                        note = Some(format!("Copied {}", url));
+                    }
+                    Ok(Action::CopyMarkdown) => {
+                        let link = format!("[{}]({})", result.title(), result.url());
+                        let _ = term.copy_to_clipboard(&link);
+                        note = Some("Copied markdown link".to_string());
+                    }
+                }
             }
+            None
         }
     }
 }
 
-impl QvaultCmdName {
-    pub fn get_handler(&self) -> fn(&[String], &mut QvaultTerminal) {
-        match self {
-            QvaultCmdName::Search => handle_search,
-            QvaultCmdName::Help => handle_help,
-            QvaultCmdName::Exit => handle_exit,
-            QvaultCmdName::History => handle_history,
-            QvaultCmdName::Set => handle_set,
-            QvaultCmdName::Ai => handle_ai,
-            QvaultCmdName::Error => handle_search,
+/// Results skipped by PageDown/PageUp in the pager
+const RESULTS_PAGE: usize = 5;
+
+/// Start the browser on a URL without waiting for it
+pub fn open_url(url: &str, opener: Option<&str>) -> io::Result<()> {
+    let default = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let mut words = opener.unwrap_or(default).split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty opener in [pager]"))?;
+    let mut child = process::Command::new(program)
+        .args(words)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reap it in the background so no zombie is left behind
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Edit distance between two short strings, used for command suggestions
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
         }
+        prev = cur;
     }
+    prev[b.len()]
 }
 
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub struct QvaultCmd {
     args: Vec<String>,
-    cmd: QvaultCmdName,
+    parsed: ParsedArgs,
+    name: &'static str,
+    rest: String,
     cmdline: String,
     count: u32,
     date: DateTime<Utc>,
@@ -194,29 +462,182 @@ impl fmt::Display for QvaultCmd {
         write!(
             f,
             "QvaultCmd {{ cmd: {}, args: {:?} }}",
-            self.cmd,
+            self.name,
             self.args
         )
     }
 }
 
 impl QvaultCmd {
-    pub fn from_input(c: &str) -> Result<Self, String> {
-        let mut toks = c.split_whitespace();
-        let cmd = toks.next().ok_or("No command found in input")?;
-        let qcmd = QvaultCmdName::from_str(cmd)?;
+    /// Parse a line of user input. A leading `/word` names the command,
+    /// anything else is a search for the whole line. User aliases and
+    /// macros from the config are expanded first.
+    pub fn from_input(c: &str, registry: &CommandRegistry, config: &QvaultConfig) -> Result<Self, String> {
+        let line = qvault_alias::expand_user_command(c, registry, config)?;
+        let mut toks = qvault_parse::tokenize(&line).map_err(|e| e.to_string())?;
+        let first = toks.first().ok_or("No command found in input")?;
 
-        let args = toks.into_iter().map(String::from).collect();
+        let (typed, rest) = if first.raw.starts_with('/') {
+            let trimmed = line.trim_start();
+            let rest = trimmed.split_once(char::is_whitespace).map_or("", |(_, r)| r);
+            (toks.remove(0).value, rest.trim().to_string())
+        } else {
+            (DEFAULT_COMMAND.to_string(), line.trim().to_string())
+        };
+        let name = registry
+            .find(&typed)
+            .map(|command| command.name())
+            .ok_or_else(|| registry.unknown_command_message(&typed))?;
+
+        let parsed = ParsedArgs::from_tokens(toks);
+        let args = parsed.args();
 
-        Ok(QvaultCmd { cmd: qcmd, args, cmdline: c.to_string(), count:1, date: Utc::now() })
+        Ok(QvaultCmd { name, args, parsed, rest, cmdline: c.to_string(), count:1, date: Utc::now() })
     }
 
-    pub fn handle_cmd(&self, term: &mut QvaultTerminal) {
-        let handler = self.cmd.get_handler();
-        handler(&self.args, term);
+    /// Parse a line that may chain commands with `|`, e.g.
+    /// `/search tokio select | /ai summarize`. A `|` only splits when the
+    /// next word is a `/command`, so a query like `rust | go` stays whole.
+    /// Aliases may expand to pipelines of their own.
+    pub fn parse_pipeline(line: &str, registry: &CommandRegistry, config: &QvaultConfig) -> Result<Vec<Self>, String> {
+        let toks = qvault_parse::tokenize(line).map_err(|e| e.to_string())?;
+        // `/alias name = /a | /b` defines a pipeline, it does not run one
+        let raw_first = toks
+            .first()
+            .filter(|t| t.raw.starts_with('/'))
+            .and_then(|t| registry.find(&t.value))
+            .is_some_and(|c| c.arg_spec().raw);
+        if raw_first {
+            return Ok(vec![Self::from_input(line, registry, config)?]);
+        }
+
+        let segments = qvault_parse::split_pipeline(toks);
+        if segments.len() > 1 && segments[0].is_empty() {
+            return Err("Nothing to pipe from, put a command before |".to_string());
+        }
+        let mut stages = Vec::new();
+        for segment in segments {
+            let text: Vec<&str> = segment.iter().map(|t| t.raw.as_str()).collect();
+            let expanded = qvault_alias::expand_user_command(&text.join(" "), registry, config)?;
+            let toks = qvault_parse::tokenize(&expanded).map_err(|e| e.to_string())?;
+            for part in qvault_parse::split_pipeline(toks) {
+                let text: Vec<&str> = part.iter().map(|t| t.raw.as_str()).collect();
+                stages.push(Self::from_input(&text.join(" "), registry, config)?);
+            }
+        }
+        Ok(stages)
+    }
+
+    /// Registered name of the command, without the slash
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Positional arguments with quotes removed
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Positional arguments as typed, quotes kept
+    pub fn text(&self) -> String {
+        self.parsed.text()
+    }
+
+    /// Everything after the command word, exactly as typed
+    pub fn rest(&self) -> &str {
+        &self.rest
+    }
+
+    /// Value of `--name=value`; a bare `--name` gives an empty string
+    pub fn flag(&self, name: &str) -> Option<&str> {
+        self.parsed
+            .flags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_deref().unwrap_or(""))
     }
 
     pub fn log_it(&self) {
-        qvault_log::log_info("Command executed:", format_args!("{}", self.cmd));
+        qvault_log::log_info("Command executed:", format_args!("{}", self.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_input_keeps_whole_query() {
+        let registry = CommandRegistry::with_builtins();
+        let config = QvaultConfig::default();
+        let cmd = QvaultCmd::from_input("rust async traits", &registry, &config).unwrap();
+        assert_eq!(cmd.name(), "search");
+        assert_eq!(cmd.text(), "rust async traits");
+
+        let cmd = QvaultCmd::from_input(r#"/search --count=3 "async traits" rust"#, &registry, &config).unwrap();
+        assert_eq!(cmd.name(), "search");
+        assert_eq!(cmd.text(), r#""async traits" rust"#);
+        assert_eq!(cmd.args(), ["async traits", "rust"]);
+        assert_eq!(cmd.flag("count"), Some("3"));
+
+        let cmd = QvaultCmd::from_input("/ai explain 'Pin<Box<T>>'", &registry, &config).unwrap();
+        assert_eq!(cmd.name(), "ai");
+        assert_eq!(cmd.args(), ["explain", "Pin<Box<T>>"]);
+
+        assert!(QvaultCmd::from_input("/ai \"unclosed", &registry, &config).is_err());
+    }
+
+    #[test]
+    fn test_parse_pipeline() {
+        let registry = CommandRegistry::with_builtins();
+        let config = QvaultConfig::default();
+
+        let stages = QvaultCmd::parse_pipeline("/search tokio select | /ai summarize the differences", &registry, &config).unwrap();
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages[0].text(), "tokio select");
+        assert_eq!(stages[1].name(), "ai");
+        assert_eq!(stages[1].text(), "summarize the differences");
+
+        let stages = QvaultCmd::parse_pipeline("rust | go \"a | /b\"", &registry, &config).unwrap();
+        assert_eq!(stages.len(), 1);
+        assert_eq!(stages[0].text(), "rust | go \"a | /b\"");
+
+        assert!(QvaultCmd::parse_pipeline("| /ai hi", &registry, &config).is_err());
+
+        let mut config = QvaultConfig::default();
+        config.macros.insert("tldr".to_string(), "/search $* | /ai summarize".to_string());
+        let stages = QvaultCmd::parse_pipeline("/tldr tokio select", &registry, &config).unwrap();
+        assert_eq!(stages.iter().map(|s| s.name()).collect::<Vec<_>>(), ["search", "ai"]);
+        let stages = QvaultCmd::parse_pipeline("/alias x = /search a | /ai b", &registry, &config).unwrap();
+        assert_eq!(stages.len(), 1);
+        assert_eq!(stages[0].rest(), "x = /search a | /ai b");
+    }
+
+    #[test]
+    fn test_unknown_command_suggestions() {
+        let registry = CommandRegistry::with_builtins();
+        assert_eq!(registry.suggest("/hist"), ["/history"]);
+        assert_eq!(registry.suggest("/serach"), ["/search"]);
+        assert_eq!(registry.suggest("/quti"), ["/exit"]);
+        assert!(registry.suggest("/zzzzzz").is_empty());
+
+        let config = QvaultConfig::default();
+        let err = QvaultCmd::from_input("/hist rust", &registry, &config).unwrap_err();
+        assert_eq!(err, "Unknown command /hist. Did you mean /history?");
+        assert_eq!(QvaultCmd::from_input("/Q", &registry, &config).unwrap().name(), "exit");
+    }
+
+    #[test]
+    fn test_registry_lists_every_command() {
+        let registry = CommandRegistry::with_builtins();
+        let mut config = QvaultConfig::default();
+        config.aliases.insert("gh".to_string(), "/search site:github.com".to_string());
+        let completions = registry.completions(&config);
+        for name in ["/help", "/history", "/exit", "/search", "/set", "/ai", "/log", "/q", "/alias", "/gh"] {
+            assert!(completions.iter().any(|c| c == name), "missing {}", name);
+        }
+        for command in registry.commands() {
+            assert!(command.usage().starts_with(&format!("/{}", command.name())));
+        }
     }
 }
@@ -1,14 +1,160 @@
-use reqwest::blocking::Client;
+use once_cell::sync::Lazy;
+use reqwest::Client;
 use serde_json::json;
 use std::env;
-use dotenv::dotenv;
 use std::fs;
+use std::io::{self, Read};
+use std::sync::Mutex;
 use serde_json::Value;
 
 use crate::qvault_log;
-use crate::qvault_log::log_info;
 
-pub fn chat_with_openai(message: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// Model used for chat completions
+const OPENAI_MODEL: &str = "gpt-3.5-turbo";
+
+/// Piped context sent to the model is cut to this many bytes
+pub const MAX_CONTEXT_BYTES: usize = 32 * 1024;
+
+/// Never buffer more than this from a pipe, whatever the limit above
+const MAX_PIPE_READ_BYTES: u64 = 16 * 1024 * 1024;
+
+/// How long the interactive UI waits for piped data before it starts
+/// without it. cron, systemd and `ssh host qvault` hand over pipes nobody
+/// writes to.
+pub const PIPE_WAIT_MS: libc::c_int = 500;
+
+/// Text piped into qvault, attached to an AI prompt as its own message
+#[derive(Debug, Clone)]
+pub struct PipedContext {
+    text: String,
+    original_len: usize,
+}
+
+impl PipedContext {
+    /// Keep at most `limit` bytes, cut on a char boundary
+    pub fn new(raw: &str, limit: usize) -> Self {
+        let mut end = raw.len().min(limit);
+        while !raw.is_char_boundary(end) {
+            end -= 1;
+        }
+        PipedContext { text: raw[..end].to_string(), original_len: raw.len() }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.text.len() < self.original_len
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        if self.is_truncated() {
+            Some(format!("piped input truncated at {} bytes", self.text.len()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Piped input from startup, waiting for the next /ai in the interactive UI
+static PENDING_CONTEXT: Lazy<Mutex<Option<PipedContext>>> = Lazy::new(|| Mutex::new(None));
+
+/// Last /ai answer, for /copy and /save
+static LAST_ANSWER: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// Read stdin when it is a pipe or file rather than a terminal.
+/// Returns `None` for a terminal or empty input. Without `wait_ms` the
+/// read blocks until the writer is done; with it, a pipe that stays
+/// silent that long gives a `TimedOut` error.
+pub fn read_piped_stdin(wait_ms: Option<libc::c_int>) -> io::Result<Option<PipedContext>> {
+    let stdin = io::stdin();
+    if termion::is_tty(&stdin) {
+        return Ok(None);
+    }
+    if let Some(millis) = wait_ms.filter(|millis| !stdin_ready(*millis)) {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, format!("no piped input within {} ms", millis)));
+    }
+    let mut buf = Vec::new();
+    stdin.lock().take(MAX_PIPE_READ_BYTES).read_to_end(&mut buf)?;
+    let raw = String::from_utf8_lossy(&buf);
+    if raw.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(PipedContext::new(&raw, MAX_CONTEXT_BYTES)))
+}
+
+/// Whether stdin has data or is at EOF within `millis`
+fn stdin_ready(millis: libc::c_int) -> bool {
+    let mut fds = [libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 }];
+    // SAFETY: one valid pollfd on stdin, which lives for the whole process
+    let ready = unsafe { libc::poll(fds.as_mut_ptr(), 1, millis) };
+    ready > 0 && (fds[0].revents & (libc::POLLIN | libc::POLLHUP)) != 0
+}
+
+pub fn set_pending_context(ctx: PipedContext) {
+    if let Ok(mut pending) = PENDING_CONTEXT.lock() {
+        *pending = Some(ctx);
+    }
+}
+
+pub fn take_pending_context() -> Option<PipedContext> {
+    PENDING_CONTEXT.lock().ok().and_then(|mut pending| pending.take())
+}
+
+pub fn set_last_answer(answer: &str) {
+    if let Ok(mut last) = LAST_ANSWER.lock() {
+        *last = Some(answer.to_string());
+    }
+}
+
+pub fn last_answer() -> Option<String> {
+    LAST_ANSWER.lock().ok().and_then(|last| last.clone())
+}
+
+pub fn has_pending_context() -> bool {
+    PENDING_CONTEXT.lock().map(|pending| pending.is_some()).unwrap_or(false)
+}
+
+/// Errors of a request, Send so they can come back from a spawned task
+pub type RequestError = Box<dyn std::error::Error + Send + Sync>;
+
+pub async fn chat_with_openai(message: String, context: Option<PipedContext>) -> Result<String, RequestError> {
+    let mut span = qvault_log::Span::start("ai", "openai chat");
+    span.field("provider", "openai");
+    span.field("model", OPENAI_MODEL);
+    if let Some(ctx) = &context {
+        span.field("context_bytes", ctx.len());
+        span.field("context_truncated", ctx.is_truncated());
+    }
+
+    let result = request_openai(&message, context.as_ref(), &mut span).await;
+    match &result {
+        Ok(_) => span.finish(),
+        Err(err) => span.fail(&err.to_string()),
+    }
+    result
+}
+
+/// Chat messages for a prompt, with piped text as a separate message before it
+fn build_messages(message: &str, context: Option<&PipedContext>) -> Vec<Value> {
+    let mut messages = Vec::new();
+    if let Some(ctx) = context {
+        let mut content = format!("Context piped from the shell:\n\n{}", ctx.text());
+        if let Some(warning) = ctx.warning() {
+            content.push_str(&format!("\n\n[{}]", warning));
+        }
+        messages.push(json!({ "role": "user", "content": content }));
+    }
+    messages.push(json!({ "role": "user", "content": message }));
+    messages
+}
+
+async fn request_openai(message: &str, context: Option<&PipedContext>, span: &mut qvault_log::Span) -> Result<String, RequestError> {
     let mut api_key = env::var("QVAULT_OPENAI_API_KEY").ok();
     if api_key.is_none() {
         // Check if qvault.env exists and load it
@@ -17,26 +163,35 @@ pub fn chat_with_openai(message: &str) -> Result<String, Box<dyn std::error::Err
             api_key = env::var("QVAULT_OPENAI_API_KEY").ok();  // Retry fetching the API key
         }
     }
+    if let Some(ref key) = api_key {
+        qvault_log::register_secret(key);
+    }
+    let api_key = api_key.ok_or("API key not found. Please set the 'QVAULT_OPENAI_API_KEY' environment variable or provide it in qvault.env.")?;
     let client = Client::new();
     let url = "https://api.openai.com/v1/chat/completions";
 
     let payload = json!({
-        "model": "gpt-3.5-turbo",
-        "messages": [
-            { "role": "user", "content": message }
-        ]
+        "model": OPENAI_MODEL,
+        "messages": build_messages(message, context)
     });
 
     let response = client
     .post(url)
     .header("Content-Type", "application/json")
-    .header("Authorization", format!("Bearer {}", api_key.unwrap()))
+    .header("Authorization", format!("Bearer {}", api_key))
     .json(&payload)
-    .send()?
-    .text()?;
+    .send()
+    .await?;
+    span.field("http_status", response.status().as_u16());
+    let response = response.text().await?;
 
     // Parse the response JSON
     let json_response: Value = serde_json::from_str(&response)?;
+    for key in ["prompt_tokens", "completion_tokens", "total_tokens"] {
+        if let Some(n) = json_response["usage"][key].as_u64() {
+            span.field(key, n);
+        }
+    }
     if let Some(content) = json_response["choices"]
         .get(0)
         .and_then(|choice| choice["message"]["content"].as_str())
@@ -46,3 +201,25 @@ pub fn chat_with_openai(message: &str) -> Result<String, Box<dyn std::error::Err
         Err("Failed to extract response content from OpenAI API".into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piped_context_truncation() {
+        let ctx = PipedContext::new("short diff", MAX_CONTEXT_BYTES);
+        assert!(!ctx.is_truncated());
+        assert!(ctx.warning().is_none());
+
+        // Cut must not land inside the two-byte 'é'
+        let ctx = PipedContext::new("abcé tail", 4);
+        assert_eq!(ctx.text(), "abc");
+        assert_eq!(ctx.warning().unwrap(), "piped input truncated at 3 bytes");
+
+        let messages = build_messages("review this", Some(&ctx));
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1]["content"], "review this");
+        assert!(messages[0]["content"].as_str().unwrap().contains("truncated"));
+    }
+}
@@ -0,0 +1,303 @@
+use crate::qvault_cmd::qvault_parse;
+use crate::qvault_cmd::{ArgSpec, CmdContext, CmdError, CmdResult, Command, CmdOutput, CommandRegistry, QvaultCmd};
+use crate::qvault_config::QvaultConfig;
+
+/// Aliases may refer to other aliases, up to this depth
+const MAX_EXPANSION_DEPTH: usize = 8;
+
+/// Expand user aliases and macros at the start of `input` until the first
+/// word is a built-in command or plain text. Built-ins always win, so a
+/// user alias can never shadow /search or /exit.
+pub fn expand_user_command(input: &str, registry: &CommandRegistry, config: &QvaultConfig) -> Result<String, String> {
+    let mut line = input.to_string();
+    for _ in 0..MAX_EXPANSION_DEPTH {
+        let trimmed = line.trim_start();
+        if !trimmed.starts_with('/') {
+            return Ok(line);
+        }
+        let (first, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+        if registry.find(first).is_some() {
+            return Ok(line);
+        }
+        let name = first.trim_start_matches('/').to_lowercase();
+
+        line = if let Some(expansion) = config.aliases.get(&name) {
+            format!("{} {}", expansion, rest.trim()).trim_end().to_string()
+        } else if let Some(template) = config.macros.get(&name) {
+            let args: Vec<String> = qvault_parse::tokenize(rest)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|t| t.value)
+                .collect();
+            expand_template(template, &args).map_err(|e| format!("Macro /{} {}", name, e))?
+        } else {
+            return Ok(line);
+        };
+    }
+    Err(format!("Alias expansion of {} does not end, check for a loop", input.trim()))
+}
+
+/// Fill `$1`..`$9` and `$*` in a macro body. `$$` is a literal dollar sign.
+/// Values are quoted where needed so multi-word arguments stay one word.
+pub fn expand_template(template: &str, args: &[String]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+            }
+            '"' | '\'' => {
+                match quote {
+                    None => quote = Some(c),
+                    Some(q) if q == c => quote = None,
+                    _ => {}
+                }
+                out.push(c);
+            }
+            '$' => match chars.peek().copied() {
+                Some(d @ '1'..='9') => {
+                    chars.next();
+                    let n = d.to_digit(10).unwrap_or(1) as usize;
+                    let value = args
+                        .get(n - 1)
+                        .ok_or_else(|| format!("needs at least {} argument(s)", n))?;
+                    out.push_str(&substitute(value, quote));
+                }
+                Some('*') => {
+                    chars.next();
+                    let all: Vec<String> = match quote {
+                        Some(_) => args.iter().map(|a| substitute(a, quote)).collect(),
+                        None => args.iter().map(|a| quote_word(a)).collect(),
+                    };
+                    out.push_str(&all.join(" "));
+                }
+                Some('$') => {
+                    chars.next();
+                    out.push('$');
+                }
+                _ => out.push('$'),
+            },
+            _ => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+fn substitute(value: &str, quote: Option<char>) -> String {
+    match quote {
+        Some('"') => value.replace('\\', "\\\\").replace('"', "\\\""),
+        Some(_) => value.to_string(),
+        None => quote_word(value),
+    }
+}
+
+/// Quote a value that would otherwise split into several words
+fn quote_word(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Which config table a definition command edits
+#[derive(Clone, Copy)]
+enum Kind {
+    Alias,
+    Macro,
+}
+
+impl Kind {
+    fn table<'a>(&self, config: &'a mut QvaultConfig) -> &'a mut std::collections::BTreeMap<String, String> {
+        match self {
+            Kind::Alias => &mut config.aliases,
+            Kind::Macro => &mut config.macros,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Kind::Alias => "alias",
+            Kind::Macro => "macro",
+        }
+    }
+
+    fn plural(&self) -> &'static str {
+        match self {
+            Kind::Alias => "aliases",
+            Kind::Macro => "macros",
+        }
+    }
+}
+
+/// Shared body of /alias and /macro: list, `name = expansion` or `--remove name`
+fn edit_definitions(kind: Kind, usage: &str, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+    let rest = cmd.rest().trim();
+
+    if rest.is_empty() {
+        let mut lines: Vec<String> = kind
+            .table(ctx.config)
+            .iter()
+            .map(|(name, body)| format!("/{} = {}", name, body))
+            .collect();
+        if lines.is_empty() {
+            lines.push(format!("No {} defined. {}", kind.plural(), usage));
+        }
+        return Ok(CmdOutput::Lines { title: kind.plural().to_string(), lines });
+    }
+
+    if let Some(name) = rest.strip_prefix("--remove") {
+        let name = name.trim().trim_start_matches('/').to_lowercase();
+        if kind.table(ctx.config).remove(&name).is_none() {
+            return Err(CmdError::Failed(format!("No {} named /{}", kind.label(), name)));
+        }
+        save(ctx)?;
+        return Ok(CmdOutput::Message(format!("Removed {} /{}", kind.label(), name)));
+    }
+
+    let (name, body) = rest
+        .split_once('=')
+        .ok_or_else(|| CmdError::Usage(usage.to_string()))?;
+    let name = name.trim().trim_start_matches('/').to_lowercase();
+    let body = body.trim();
+    if name.is_empty() || body.is_empty() {
+        return Err(CmdError::Usage(usage.to_string()));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(CmdError::Failed(format!("/{} is not a valid {} name", name, kind.label())));
+    }
+    if ctx.registry.find(&name).is_some() {
+        return Err(CmdError::Failed(format!("/{} is a built-in command", name)));
+    }
+    qvault_parse::tokenize(body).map_err(|e| CmdError::Failed(e.to_string()))?;
+
+    // A name lives in one table only
+    ctx.config.aliases.remove(&name);
+    ctx.config.macros.remove(&name);
+    kind.table(ctx.config).insert(name.clone(), body.to_string());
+    save(ctx)?;
+    Ok(CmdOutput::Message(format!("Saved {} /{} = {}", kind.label(), name, body)))
+}
+
+fn save(ctx: &mut CmdContext) -> Result<(), CmdError> {
+    ctx.config
+        .save()
+        .map_err(|e| CmdError::Failed(format!("Cannot save config: {}", e)))
+}
+
+pub struct AliasCmd;
+
+impl Command for AliasCmd {
+    fn name(&self) -> &'static str {
+        "alias"
+    }
+
+    fn help(&self) -> &'static str {
+        "List, add or remove command aliases"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/alias [name = /command args | --remove name]"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec::RAW
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        edit_definitions(Kind::Alias, self.usage(), cmd, ctx)
+    }
+}
+
+pub struct MacroCmd;
+
+impl Command for MacroCmd {
+    fn name(&self) -> &'static str {
+        "macro"
+    }
+
+    fn help(&self) -> &'static str {
+        "Aliases with $1..$9 and $* parameters"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/macro [name = /command \"$1\" ... | --remove name]"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec::RAW
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        edit_definitions(Kind::Macro, self.usage(), cmd, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> QvaultConfig {
+        let mut config = QvaultConfig::default();
+        config.aliases.insert("gh".to_string(), "/search site:github.com".to_string());
+        config.aliases.insert("g".to_string(), "/gh".to_string());
+        config.aliases.insert("loop".to_string(), "/loop".to_string());
+        config.aliases.insert("search".to_string(), "/exit".to_string());
+        config.macros.insert("rfc".to_string(), "/search \"RFC $1\" site:ietf.org".to_string());
+        config.macros.insert("site".to_string(), "/search site:$1 $*".to_string());
+        config
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let registry = CommandRegistry::with_builtins();
+        let config = config();
+        let expand = |s: &str| expand_user_command(s, &registry, &config);
+
+        assert_eq!(expand("/gh ripgrep").unwrap(), "/search site:github.com ripgrep");
+        assert_eq!(expand("/g ripgrep").unwrap(), "/search site:github.com ripgrep");
+        assert_eq!(expand("/search tokio").unwrap(), "/search tokio");
+        assert_eq!(expand("plain words").unwrap(), "plain words");
+        assert_eq!(expand("/nope x").unwrap(), "/nope x");
+        assert!(expand("/loop").is_err());
+    }
+
+    #[test]
+    fn test_expand_macros() {
+        let registry = CommandRegistry::with_builtins();
+        let config = config();
+        let expand = |s: &str| expand_user_command(s, &registry, &config);
+
+        assert_eq!(expand("/rfc 9110").unwrap(), "/search \"RFC 9110\" site:ietf.org");
+        assert_eq!(expand("/site docs.rs \"async fn\"").unwrap(), "/search site:docs.rs docs.rs \"async fn\"");
+        assert_eq!(expand("/rfc").unwrap_err(), "Macro /rfc needs at least 1 argument(s)");
+        assert_eq!(expand_template("cost $$5 $x", &[]).unwrap(), "cost $5 $x");
+    }
+
+    #[test]
+    fn test_list_macros() {
+        let registry = CommandRegistry::with_builtins();
+        let mut config = config();
+        let list = |config: &mut QvaultConfig| {
+            let stages = QvaultCmd::parse_pipeline("/macro", &registry, config).unwrap();
+            match registry.run_pipeline(&stages, None, config) {
+                Ok(CmdOutput::Lines { title, lines }) => (title, lines),
+                other => panic!("unexpected /macro output {:?}", other),
+            }
+        };
+
+        let (title, lines) = list(&mut config);
+        assert_eq!(title, "macros");
+        assert_eq!(lines, ["/rfc = /search \"RFC $1\" site:ietf.org", "/site = /search site:$1 $*"]);
+
+        config.macros.clear();
+        let (_, lines) = list(&mut config);
+        assert!(lines[0].starts_with("No macros defined. "));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+/// Bangs that work without any config. `[bangs]` in qvault.toml adds to
+/// or overrides these.
+const DEFAULT_BANGS: &[(&str, &str)] = &[
+    ("rs", "https://docs.rs/releases/search?query={}"),
+    ("crates", "https://crates.io/search?q={}"),
+    ("std", "https://doc.rust-lang.org/std/?search={}"),
+    ("gh", "site:github.com"),
+    ("so", "site:stackoverflow.com"),
+    ("mdn", "site:developer.mozilla.org"),
+    ("w", "https://en.wikipedia.org/wiki/Special:Search?search={}"),
+];
+
+/// Where a bang sends the query
+#[derive(Debug, Clone, PartialEq)]
+pub enum BangTarget {
+    /// Search again with this filter in front, e.g. `site:github.com ripgrep`
+    Search(String),
+    /// Skip the search and go straight to this URL
+    Url(String),
+}
+
+/// Default bangs merged with the user's table
+pub fn bang_table(user: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut table: BTreeMap<String, String> = DEFAULT_BANGS
+        .iter()
+        .map(|(name, target)| (name.to_string(), target.to_string()))
+        .collect();
+    for (name, target) in user {
+        table.insert(name.trim_start_matches('!').to_lowercase(), target.clone());
+    }
+    table
+}
+
+/// Find a known `!bang` anywhere in the query, DuckDuckGo style.
+/// Table entries containing `{}` are URL templates, anything else is a
+/// filter prepended to the search. Unknown bangs stay in the query.
+pub fn resolve(query: &str, table: &BTreeMap<String, String>) -> Option<BangTarget> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let (pos, template) = words.iter().enumerate().find_map(|(i, word)| {
+        let name = word.strip_prefix('!')?.to_lowercase();
+        table.get(&name).map(|t| (i, t))
+    })?;
+
+    let rest: Vec<&str> = words
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != pos)
+        .map(|(_, w)| *w)
+        .collect();
+    let rest = rest.join(" ");
+
+    if template.contains("{}") {
+        Some(BangTarget::Url(template.replace("{}", &encode_query(&rest))))
+    } else {
+        Some(BangTarget::Search(format!("{} {}", template, rest).trim().to_string()))
+    }
+}
+
+/// Percent-encode a query for use inside a URL
+fn encode_query(text: &str) -> String {
+    let mut out = String::new();
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b' ' => out.push('+'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_bangs() {
+        let mut user = BTreeMap::new();
+        user.insert("!tk".to_string(), "https://docs.rs/tokio/latest/tokio/?search={}".to_string());
+        user.insert("so".to_string(), "site:rust-lang.org".to_string());
+        let table = bang_table(&user);
+
+        assert_eq!(
+            resolve("!rs tokio select", &table),
+            Some(BangTarget::Url("https://docs.rs/releases/search?query=tokio+select".to_string()))
+        );
+        assert_eq!(
+            resolve("ripgrep !GH", &table),
+            Some(BangTarget::Search("site:github.com ripgrep".to_string()))
+        );
+        assert_eq!(
+            resolve("borrow checker !so", &table),
+            Some(BangTarget::Search("site:rust-lang.org borrow checker".to_string()))
+        );
+        assert_eq!(
+            resolve("!tk c++ & co", &table),
+            Some(BangTarget::Url("https://docs.rs/tokio/latest/tokio/?search=c%2B%2B+%26+co".to_string()))
+        );
+        assert_eq!(resolve("hello !nope world", &table), None);
+    }
+}
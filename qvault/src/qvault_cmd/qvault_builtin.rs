@@ -0,0 +1,469 @@
+use crate::qvault_cmd::qvault_ai;
+use crate::qvault_cmd::qvault_alias::{AliasCmd, MacroCmd};
+use crate::qvault_cmd::qvault_bang::{self, BangTarget};
+use crate::qvault_cmd::qvault_code::{CopyCmd, SaveCmd};
+use crate::qvault_cmd::qvault_search;
+use crate::qvault_cmd::{ArgSpec, CmdContext, CmdError, CmdResult, Command, CmdOutput, CommandRegistry, QvaultCmd};
+use crate::qvault_config::QvaultConfig;
+use crate::qvault_keymap::Keymap;
+use crate::qvault_log;
+use crate::qvault_script;
+use crate::qvault_theme::{self, Theme};
+use crate::qvault_tui::TAB_COUNT;
+
+/// Register the commands that ship with qvault, in /help order
+pub fn register_all(registry: &mut CommandRegistry) {
+    registry.register(Box::new(HelpCmd));
+    registry.register(Box::new(HistoryCmd));
+    registry.register(Box::new(ExitCmd));
+    registry.register(Box::new(SearchCmd));
+    registry.register(Box::new(SetCmd));
+    registry.register(Box::new(AiCmd));
+    registry.register(Box::new(LogCmd));
+    registry.register(Box::new(SourceCmd));
+    registry.register(Box::new(AliasCmd));
+    registry.register(Box::new(MacroCmd));
+    registry.register(Box::new(CopyCmd));
+    registry.register(Box::new(SaveCmd));
+    registry.register(Box::new(ThemeCmd));
+    registry.register(Box::new(TabCmd));
+}
+
+struct HelpCmd;
+
+impl Command for HelpCmd {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["?"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Show this help message"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/help [command]"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec { min_args: 0, max_args: Some(1), flags: &[], raw: false }
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let mut hstrs: Vec<String> = vec![];
+
+        // `/help search` describes one command in detail
+        if let Some(word) = cmd.args().first() {
+            let command = ctx
+                .registry
+                .find(word)
+                .ok_or_else(|| CmdError::Failed(format!("No help for unknown command {}", word)))?;
+            hstrs.push(command.usage().to_string());
+            hstrs.push(command.help().to_string());
+            if !command.aliases().is_empty() {
+                let aliases: Vec<String> = command.aliases().iter().map(|a| format!("/{}", a)).collect();
+                hstrs.push(format!("Aliases: {}", aliases.join(", ")));
+            }
+            let flags = command.arg_spec().flags;
+            if !flags.is_empty() {
+                let flags: Vec<String> = flags.iter().map(|f| format!("--{}", f)).collect();
+                hstrs.push(format!("Options: {}", flags.join(", ")));
+            }
+            return Ok(CmdOutput::Lines { title: "Help".to_string(), lines: hstrs });
+        }
+
+        Ok(CmdOutput::Lines { title: "Help".to_string(), lines: overview(ctx.registry, ctx.config) })
+    }
+}
+
+/// Lines of a bare /help: every command, then the keys and the mouse
+pub fn overview(registry: &CommandRegistry, config: &QvaultConfig) -> Vec<String> {
+    let mut hstrs: Vec<String> = vec![];
+    for command in registry.commands() {
+        qvault_log::log_info("CMD: ", format_args!("/{} - {}", command.name(), command.help()));
+        let aliases = command.aliases();
+        if aliases.is_empty() {
+            hstrs.push(format!("/{} - {}", command.name(), command.help()));
+        } else {
+            let aliases: Vec<String> = aliases.iter().map(|a| format!("/{}", a)).collect();
+            hstrs.push(format!("/{} ({}) - {}", command.name(), aliases.join(", "), command.help()));
+        }
+    }
+
+    hstrs.push("Chain commands with |, e.g. /search tokio | /ai summarize".to_string());
+
+    // Pager and scroll keys, as set up by [keys] in the config
+    match Keymap::from_config(config) {
+        Ok(keymap) => {
+            hstrs.push(format!("Keys ({}):", keymap.preset));
+            hstrs.extend(keymap.describe().into_iter().map(|line| format!("  {}", line)));
+        }
+        Err(err) => hstrs.push(format!("Keys: {}", err)),
+    }
+    hstrs.push("Alt-1..Alt-9 switch tabs, each keeps its own output".to_string());
+    hstrs.push("Mouse: wheel scrolls, click a result to select it and again to open it,".to_string());
+    hstrs.push("  click a link to open it, the left or right half of the bar to page".to_string());
+    hstrs
+}
+
+struct HistoryCmd;
+
+impl Command for HistoryCmd {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn help(&self) -> &'static str {
+        "History of commands"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/history"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec::NONE
+    }
+
+    fn execute(&self, _cmd: &QvaultCmd, _ctx: &mut CmdContext) -> CmdResult {
+        Ok(CmdOutput::Text { title: "History command".to_string(), body: String::new() })
+    }
+}
+
+struct ExitCmd;
+
+impl Command for ExitCmd {
+    fn name(&self) -> &'static str {
+        "exit"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["quit", "q"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Exit from Qvault"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/exit"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec::NONE
+    }
+
+    fn execute(&self, _cmd: &QvaultCmd, _ctx: &mut CmdContext) -> CmdResult {
+        Ok(CmdOutput::Exit)
+    }
+}
+
+struct SearchCmd;
+
+impl Command for SearchCmd {
+    fn name(&self) -> &'static str {
+        "search"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["s"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Search using Brave Search, !bangs like !rs or !gh jump to a site"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/search [--count=N] <query> [!bang]"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec { min_args: 1, max_args: None, flags: &["count"], raw: false }
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let text = cmd.text();
+        let query = match qvault_bang::resolve(&text, &qvault_bang::bang_table(&ctx.config.bangs)) {
+            Some(BangTarget::Url(url)) => {
+                qvault_log::log_query("Bang resolved for: ", &text);
+                return Ok(CmdOutput::Link { title: text, url });
+            }
+            Some(BangTarget::Search(query)) => query,
+            None => text,
+        };
+        qvault_log::log_query("Searching for: ", &query);
+        let count = match cmd.flag("count").map(str::parse::<u32>) {
+            Some(Ok(n)) => Some(n),
+            Some(Err(_)) => return Err(CmdError::Usage(self.usage().to_string())),
+            None => None,
+        };
+
+        let result = ctx
+            .request("Searching", qvault_search::search_brave(query.clone(), count))?
+            .map_err(|err| CmdError::Failed(format!("Search failed: {}", err)))?;
+        if let Some(err) = result.error() {
+            return Err(CmdError::Failed(format!("Search failed: {}", err)));
+        }
+        if result.count() == 0 {
+            return Err(CmdError::Failed(format!("No results for {}", query)));
+        }
+        Ok(CmdOutput::Results { query, result })
+    }
+}
+
+struct SetCmd;
+
+impl Command for SetCmd {
+    fn name(&self) -> &'static str {
+        "set"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["settings"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Settings for Qvault"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/set"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec::NONE
+    }
+
+    fn execute(&self, _cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let term = ctx.term(self.name())?;
+        let _ = term.clear_output_screen();
+        let _ = term.show_output_title("QVault Settings".to_string());
+        term.tui_show_settings()
+            .map_err(|err| CmdError::Failed(format!("Settings failed: {}", err)))?;
+        Ok(CmdOutput::Nothing)
+    }
+}
+
+struct AiCmd;
+
+impl Command for AiCmd {
+    fn name(&self) -> &'static str {
+        "ai"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["ask"]
+    }
+
+    fn help(&self) -> &'static str {
+        "AI lookup using OpenAI"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/ai <prompt>"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec { min_args: 1, max_args: None, flags: &[], raw: false }
+    }
+
+    fn reads_input(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let prompt = cmd.text();
+        qvault_log::log_query("AI command executed:", &prompt);
+
+        // Output of a pipeline stage, else input piped in at startup
+        let context = match ctx.input.take().and_then(|input| input.to_context()) {
+            Some(text) => Some(qvault_ai::PipedContext::new(&text, qvault_ai::MAX_CONTEXT_BYTES)),
+            None => qvault_ai::take_pending_context(),
+        };
+        if let Some(warning) = context.as_ref().and_then(|ctx| ctx.warning()) {
+            match ctx.term.as_deref_mut() {
+                Some(term) => {
+                    let _ = term.write_bar_message(&warning);
+                }
+                None => qvault_log::log_warn(warning),
+            }
+        }
+        let result = ctx
+            .request("Asking the AI", qvault_ai::chat_with_openai(prompt, context))?
+            .map_err(|err| CmdError::Failed(format!("AI request failed: {}", err)))?;
+        qvault_ai::set_last_answer(&result);
+        Ok(CmdOutput::Markdown { title: "AI Response".to_string(), body: result })
+    }
+}
+
+struct LogCmd;
+
+impl Command for LogCmd {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["logs"]
+    }
+
+    fn help(&self) -> &'static str {
+        "View the qvault log [level] [text]"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/log [error|warn|info|debug] [text]"
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let term = ctx.term(self.name())?;
+        let _ = term.clear_output_screen();
+        let mut filter = qvault_log::LogFilter::default();
+        let args = cmd.args();
+        let mut rest = args;
+        // Optional leading level, e.g. `/log warn timeout`
+        if let Some(level) = args.first().and_then(|a| a.parse::<log::Level>().ok()) {
+            filter.min_level = level;
+            rest = &args[1..];
+        }
+        filter.needle = rest.join(" ");
+
+        term.tui_show_log(qvault_log::LOG_PATH, filter)
+            .map_err(|err| CmdError::Failed(format!("Cannot open {}: {}", qvault_log::LOG_PATH, err)))?;
+        Ok(CmdOutput::Nothing)
+    }
+}
+
+struct SourceCmd;
+
+impl Command for SourceCmd {
+    fn name(&self) -> &'static str {
+        "source"
+    }
+
+    fn help(&self) -> &'static str {
+        "Run the commands in a script file and save a report"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/source [--report=FILE] <file>"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec { min_args: 1, max_args: Some(1), flags: &["report"], raw: false }
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let script = &cmd.args()[0];
+        let report_path = match cmd.flag("report") {
+            Some("") => return Err(CmdError::Usage(self.usage().to_string())),
+            Some(path) => path.to_string(),
+            None => qvault_script::default_report_path(script),
+        };
+        if let Some(term) = ctx.term.as_deref_mut() {
+            let _ = term.write_bar_message(&format!("Running {} ...", script));
+        }
+
+        let report = qvault_script::run_script(script, ctx.registry, ctx.config).map_err(CmdError::Failed)?;
+        report.save(&report_path).map_err(CmdError::Failed)?;
+
+        let mut lines = vec![report.summary(), format!("Report saved to {}", report_path), String::new()];
+        for entry in &report.entries {
+            let mark = if entry.result.is_ok() { "ok  " } else { "FAIL" };
+            lines.push(format!("{} {:>3}: {}", mark, entry.line, entry.command));
+        }
+        Ok(CmdOutput::Lines { title: "Script".to_string(), lines })
+    }
+}
+
+struct ThemeCmd;
+
+impl Command for ThemeCmd {
+    fn name(&self) -> &'static str {
+        "theme"
+    }
+
+    fn help(&self) -> &'static str {
+        "List color themes or switch to one"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/theme [name]"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec { min_args: 0, max_args: Some(1), flags: &[], raw: false }
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let Some(name) = cmd.args().first() else {
+            let current = ctx.config.theme.as_deref().unwrap_or("dark");
+            let lines = qvault_theme::theme_names(ctx.config)
+                .into_iter()
+                .map(|name| if name == current { format!("{} (current)", name) } else { name })
+                .collect();
+            return Ok(CmdOutput::Lines { title: "Themes".to_string(), lines });
+        };
+
+        let theme = Theme::load(name, ctx.config).map_err(CmdError::Failed)?;
+        ctx.config.theme = Some(theme.name.clone());
+        ctx.config
+            .save()
+            .map_err(|e| CmdError::Failed(format!("Cannot save config: {}", e)))?;
+        let msg = format!("Theme {}", theme.name);
+        if let Some(term) = ctx.term.as_deref_mut() {
+            term.set_theme(theme);
+        }
+        Ok(CmdOutput::Message(msg))
+    }
+}
+
+struct TabCmd;
+
+impl Command for TabCmd {
+    fn name(&self) -> &'static str {
+        "tab"
+    }
+
+    fn help(&self) -> &'static str {
+        "List tabs or switch to one, Alt-1..Alt-9 does the same"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/tab [1-9]"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec { min_args: 0, max_args: Some(1), flags: &[], raw: false }
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let term = ctx.term(self.name())?;
+        let Some(arg) = cmd.args().first() else {
+            // Listed in the bar, output would replace what the tab shows
+            let active = term.active_tab() + 1;
+            let tabs: Vec<String> = term
+                .tab_labels()
+                .into_iter()
+                .map(|(n, label)| if n == active { format!("[{} {}]", n, label) } else { format!("{} {}", n, label) })
+                .collect();
+            return Ok(CmdOutput::Message(format!("Tabs: {}", tabs.join("  "))));
+        };
+        let n = match arg.parse::<usize>() {
+            Ok(n) if (1..=TAB_COUNT).contains(&n) => n,
+            _ => return Err(CmdError::Usage(self.usage().to_string())),
+        };
+
+        term.switch_tab(n - 1)
+            .map_err(|err| CmdError::Failed(format!("Cannot switch tabs: {}", err)))?;
+        // Back into the pager or scroll view, where the tab was left
+        if let Some((query, result)) = term.tab_results() {
+            return Ok(CmdOutput::Results { query, result });
+        }
+        let _ = term.scroll_output();
+        Ok(CmdOutput::Message(format!("Tab {}", n)))
+    }
+}
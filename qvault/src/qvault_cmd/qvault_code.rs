@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::Path;
+
+use crate::qvault_cmd::qvault_ai;
+use crate::qvault_cmd::{ArgSpec, CmdContext, CmdError, CmdResult, Command, CmdOutput, QvaultCmd};
+use crate::qvault_markdown::{self, CodeBlock};
+
+/// Code block N of the piped input, or of the last /ai answer
+fn pick_block(cmd: &QvaultCmd, ctx: &mut CmdContext, usage: &str) -> Result<CodeBlock, CmdError> {
+    let n: usize = cmd.args()[0]
+        .parse()
+        .map_err(|_| CmdError::Usage(usage.to_string()))?;
+    let text = ctx
+        .input
+        .take()
+        .and_then(|input| input.to_context())
+        .or_else(qvault_ai::last_answer)
+        .ok_or_else(|| CmdError::Failed("No AI answer yet, ask with /ai first".to_string()))?;
+
+    let blocks = qvault_markdown::code_blocks(&text);
+    match blocks.len() {
+        0 => Err(CmdError::Failed("The answer has no code blocks".to_string())),
+        count if n == 0 || n > count => Err(CmdError::Failed(format!("No code block {}, the answer has {}", n, count))),
+        _ => Ok(blocks[n - 1].clone()),
+    }
+}
+
+/// `rust, 12 lines` for status messages
+fn describe(block: &CodeBlock) -> String {
+    let lines = block.code.lines().count();
+    let lang = if block.lang.is_empty() { "text" } else { &block.lang };
+    format!("{}, {} line{}", lang, lines, if lines == 1 { "" } else { "s" })
+}
+
+pub struct CopyCmd;
+
+impl Command for CopyCmd {
+    fn name(&self) -> &'static str {
+        "copy"
+    }
+
+    fn help(&self) -> &'static str {
+        "Copy a code block of the last AI answer to the clipboard"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/copy <N>"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec { min_args: 1, max_args: Some(1), flags: &[], raw: false }
+    }
+
+    fn reads_input(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let block = pick_block(cmd, ctx, self.usage())?;
+        ctx.term(self.name())?
+            .copy_to_clipboard(&block.code)
+            .map_err(|err| CmdError::Failed(format!("Cannot copy: {}", err)))?;
+        Ok(CmdOutput::Message(format!("Copied code block {} ({})", cmd.args()[0], describe(&block))))
+    }
+}
+
+pub struct SaveCmd;
+
+impl Command for SaveCmd {
+    fn name(&self) -> &'static str {
+        "save"
+    }
+
+    fn help(&self) -> &'static str {
+        "Write a code block of the last AI answer to a file"
+    }
+
+    fn usage(&self) -> &'static str {
+        "/save [--force] <N> <file>"
+    }
+
+    fn arg_spec(&self) -> ArgSpec {
+        ArgSpec { min_args: 2, max_args: Some(2), flags: &["force"], raw: false }
+    }
+
+    fn reads_input(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, cmd: &QvaultCmd, ctx: &mut CmdContext) -> CmdResult {
+        let block = pick_block(cmd, ctx, self.usage())?;
+        let path = &cmd.args()[1];
+        if Path::new(path).exists() && cmd.flag("force").is_none() {
+            return Err(CmdError::Failed(format!("{} exists, use /save --force to overwrite it", path)));
+        }
+        fs::write(path, &block.code).map_err(|err| CmdError::Failed(format!("Cannot write {}: {}", path, err)))?;
+        Ok(CmdOutput::Message(format!("Saved code block {} ({}) to {}", cmd.args()[0], describe(&block), path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qvault_cmd::CommandRegistry;
+    use crate::qvault_config::QvaultConfig;
+
+    #[test]
+    fn test_save_code_block() {
+        let registry = CommandRegistry::with_builtins();
+        let mut config = QvaultConfig::default();
+        let answer = || CmdOutput::Markdown {
+            title: "AI Response".to_string(),
+            body: "Use this:\n\n```toml\n[a]\n```\n\n```rust\nfn main() {}\n```\n".to_string(),
+        };
+        let path = std::env::temp_dir().join(format!("qvault_code_{}.rs", std::process::id()));
+        let path = path.to_str().unwrap();
+        let run = |line: &str, config: &mut QvaultConfig| {
+            let cmd = QvaultCmd::from_input(line, &registry, config).unwrap();
+            registry.dispatch(&cmd, Some(answer()), None, config)
+        };
+
+        let saved = run(&format!("/save 2 {}", path), &mut config).unwrap();
+        assert!(matches!(saved, CmdOutput::Message(msg) if msg.contains("(rust, 1 line)")));
+        assert_eq!(fs::read_to_string(path).unwrap(), "fn main() {}\n");
+        let exists = run(&format!("/save 1 {}", path), &mut config).unwrap_err();
+        assert!(exists.to_string().contains("--force"));
+        run(&format!("/save --force 1 {}", path), &mut config).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "[a]\n");
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            run("/save 3 x.rs", &mut config).unwrap_err(),
+            CmdError::Failed("No code block 3, the answer has 2".to_string())
+        );
+        assert_eq!(run("/copy 1", &mut config).unwrap_err().to_string(), "/copy needs the full-screen UI");
+    }
+}
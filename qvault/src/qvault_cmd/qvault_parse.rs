@@ -0,0 +1,193 @@
+use std::fmt;
+
+/// One word of user input, unquoted, along with the text it was typed as
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub value: String,
+    pub raw: String,
+    pub quoted: bool,
+}
+
+impl Token {
+    /// The word for a search query: escapes resolved, and a quoted word put
+    /// back in double quotes so it stays a phrase. `site="a b"` keeps the
+    /// name outside the quotes.
+    fn query_word(&self) -> String {
+        if !self.quoted {
+            return self.value.clone();
+        }
+        match self.value.split_once('=') {
+            Some((name, phrase)) if !self.raw.starts_with(['"', '\'']) => format!("{}=\"{}\"", name, phrase),
+            _ => format!("\"{}\"", self.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    UnterminatedQuote(char),
+    TrailingEscape,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnterminatedQuote(q) => write!(f, "Missing closing {} quote", q),
+            ParseError::TrailingEscape => write!(f, "Input ends with a lone backslash"),
+        }
+    }
+}
+
+/// Split input into words the way a shell would: whitespace separates words,
+/// single and double quotes group them, and a backslash escapes the next char
+/// (inside double quotes only `"` and `\` are escaped). A quote only opens at
+/// the start of a word or right after `=`, so apostrophes in prose like
+/// `what's` stay literal.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut value = String::new();
+        let mut quoted = false;
+        let mut end = start;
+        while let Some(&(i, c)) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            chars.next();
+            end = i + c.len_utf8();
+            match c {
+                '\\' => {
+                    let (i, next) = chars.next().ok_or(ParseError::TrailingEscape)?;
+                    value.push(next);
+                    end = i + next.len_utf8();
+                }
+                '"' | '\'' if value.is_empty() || value.ends_with('=') => {
+                    quoted = true;
+                    let quote = c;
+                    loop {
+                        let (i, inner) = chars.next().ok_or(ParseError::UnterminatedQuote(quote))?;
+                        end = i + inner.len_utf8();
+                        if inner == quote {
+                            break;
+                        }
+                        if inner == '\\' && quote == '"' {
+                            if let Some(&(_, esc)) = chars.peek() {
+                                if esc == '"' || esc == '\\' {
+                                    chars.next();
+                                    value.push(esc);
+                                    continue;
+                                }
+                            }
+                        }
+                        value.push(inner);
+                    }
+                }
+                _ => value.push(c),
+            }
+        }
+        tokens.push(Token { value, raw: input[start..end].to_string(), quoted });
+    }
+
+    Ok(tokens)
+}
+
+/// Split tokens at each unquoted `|` that is followed by a `/command`
+pub fn split_pipeline(tokens: Vec<Token>) -> Vec<Vec<Token>> {
+    let mut stages = vec![Vec::new()];
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(tok) = iter.next() {
+        let next_is_cmd = iter.peek().is_some_and(|next| next.raw.starts_with('/'));
+        if tok.raw == "|" && next_is_cmd {
+            stages.push(Vec::new());
+        } else if let Some(stage) = stages.last_mut() {
+            stage.push(tok);
+        }
+    }
+    stages
+}
+
+/// Tokens split into positional arguments and `--flag` / `--flag=value` options.
+/// A bare `--` ends option parsing, everything after it is positional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedArgs {
+    pub positional: Vec<Token>,
+    pub flags: Vec<(String, Option<String>)>,
+}
+
+impl ParsedArgs {
+    pub fn from_tokens(tokens: Vec<Token>) -> Self {
+        let mut parsed = ParsedArgs::default();
+        let mut options_done = false;
+        for tok in tokens {
+            let is_flag = !options_done && !tok.quoted && tok.value.starts_with("--");
+            if !is_flag {
+                parsed.positional.push(tok);
+                continue;
+            }
+            if tok.value == "--" {
+                options_done = true;
+                continue;
+            }
+            let body = &tok.value[2..];
+            match body.split_once('=') {
+                Some((name, value)) => parsed.flags.push((name.to_string(), Some(value.to_string()))),
+                None => parsed.flags.push((body.to_string(), None)),
+            }
+        }
+        parsed
+    }
+
+    /// Unquoted positional words
+    pub fn args(&self) -> Vec<String> {
+        self.positional.iter().map(|t| t.value.clone()).collect()
+    }
+
+    /// Positional words unescaped, quoted words in double quotes, for
+    /// search providers that understand phrase queries
+    pub fn text(&self) -> String {
+        self.positional.iter().map(Token::query_word).collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(input: &str) -> Vec<String> {
+        tokenize(input).unwrap().into_iter().map(|t| t.value).collect()
+    }
+
+    #[test]
+    fn test_tokenize_quotes_and_escapes() {
+        assert_eq!(values("  rust   async traits "), vec!["rust", "async", "traits"]);
+        assert_eq!(values(r#"/search "rust async" traits"#), vec!["/search", "rust async", "traits"]);
+        assert_eq!(values(r"it\'s 'a b' c\ d"), vec!["it's", "a b", "c d"]);
+        assert_eq!(values(r#""say \"hi\"""#), vec![r#"say "hi""#]);
+        assert_eq!(values("what's a closure"), vec!["what's", "a", "closure"]);
+        assert_eq!(values(r#"--site="rust lang""#), vec!["--site=rust lang"]);
+        assert_eq!(tokenize(r#"/ai "unclosed"#), Err(ParseError::UnterminatedQuote('"')));
+        assert_eq!(tokenize(r"oops\"), Err(ParseError::TrailingEscape));
+    }
+
+    #[test]
+    fn test_parsed_args_flags_and_text() {
+        let parsed = ParsedArgs::from_tokens(tokenize(r#"--count=5 rust "async traits" --fresh -- --literal"#).unwrap());
+        assert_eq!(parsed.flags, vec![
+            ("count".to_string(), Some("5".to_string())),
+            ("fresh".to_string(), None),
+        ]);
+        assert_eq!(parsed.args(), vec!["rust", "async traits", "--literal"]);
+        assert_eq!(parsed.text(), r#"rust "async traits" --literal"#);
+
+        // Escapes never reach the provider, quoted words stay phrases
+        let parsed = ParsedArgs::from_tokens(tokenize(r#"foo\ bar it\'s 'a b' site="x y""#).unwrap());
+        assert_eq!(parsed.text(), r#"foo bar it's "a b" site="x y""#);
+    }
+}
@@ -1,15 +1,13 @@
-use reqwest::{blocking::get, header, Error};
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
-use dotenv::dotenv;
 use std::fmt;
 use serde_json::Value;
 
 use crate::qvault_log;
 use crate::qvault_log::log_info;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SearchResult {
     error: Option<String>,
     status: Option<u16>,
@@ -17,24 +15,41 @@ pub struct SearchResult {
     items: Option<Vec<SearchItem>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct SearchItem {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchItem {
     title: String,
     url: String,
+    #[serde(default)]
+    description: String,
 }
 
 impl SearchItem {
     // Constructor method for the struct
-    fn new(title: String, url: String) -> Self {
+    fn new(title: String, url: String, description: String) -> Self {
         Self {
-            title: title,
-            url: url,
+            title,
+            url,
+            description,
         }
     }
 
-    // A method to display the SearchItem
-    fn display(&self) {
-        println!("Title: {}, Url: {}", self.title, self.url);
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Host part of the URL without `www.`, for compact listings
+    pub fn domain(&self) -> &str {
+        let rest = self.url.split_once("://").map_or(self.url.as_str(), |(_, r)| r);
+        let host = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+        host.strip_prefix("www.").unwrap_or(host)
     }
 }
 
@@ -49,22 +64,31 @@ impl SearchItemList {
         Self { list: Vec::new() }
     }
 
-    // Method to add a person to the list
-    fn add_search_item(&mut self, title: Value, url: Value) {
-        let si = SearchItem::new(title.to_string(), url.to_string());
+    // Method to add a result to the list. Brave marks up matches in
+    // descriptions with <strong>, which is stripped here.
+    fn add_search_item(&mut self, title: &Value, url: &Value, description: &Value) {
+        let text = |v: &Value| v.as_str().map(strip_tags).unwrap_or_default();
+        let si = SearchItem::new(text(title), text(url), text(description));
         self.list.push(si);
     }
+}
 
-    // Method to display all people
-    fn display_all(&self) {
-        for item in &self.list {
-            item.display();
+fn strip_tags(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
         }
     }
+    out.replace("&amp;", "&").replace("&quot;", "\"").replace("&#x27;", "'").replace("&lt;", "<").replace("&gt;", ">")
 }
 
 /*
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SearchResult {
     title: String,
     url: String,
@@ -77,17 +101,17 @@ impl fmt::Display for SearchResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Format the error and status if present
         if let Some(ref err) = self.error {
-            write!(f, "Error: {}\n", err)?;
+            writeln!(f, "Error: {}", err)?;
         }
 
         if let Some(status_code) = self.status {
-            write!(f, "Status: {}\n", status_code)?;
+            writeln!(f, "Status: {}", status_code)?;
         }
 
         // Format the items if present
         if let Some(ref items) = self.items {
             for item in items {
-                write!(f, "{}\n", item)?;
+                writeln!(f, "{}", item)?;
             }
         }
 
@@ -114,16 +138,38 @@ impl SearchResult {
         if let Some(items) = &self.items {
             return &items[self.index].url;
         }
-        &"https://google.com"
+        "https://google.com"
     }
 
     pub fn snippet(&self) -> &str {
         if let Some(items) = &self.items {
-            return &items[self.index].title;
+            let item = &items[self.index];
+            if item.description.is_empty() {
+                return &item.title;
+            }
+            return &item.description;
         }
         "This is a snippet from the search result"
     }
 
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn items(&self) -> &[SearchItem] {
+        self.items.as_deref().unwrap_or(&[])
+    }
+
+    /// Position of the current item
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Make item `index` current, clamped to the last item
+    pub fn select(&mut self, index: usize) {
+        self.index = index.min(self.count().saturating_sub(1));
+    }
+
     pub fn count(&self) -> usize {
         if let Some(items) = &self.items {
             return items.len();
@@ -152,14 +198,19 @@ impl SearchResult {
     }
 }
 
-#[derive(Serialize, Debug)]
-pub struct SearchErrorResponse {
-    error: String,
-}
-
-impl SearchErrorResponse {
-    pub fn error(&self) -> &str {
-        &self.error
+#[cfg(test)]
+impl SearchResult {
+    /// Result built from (title, url, description) triples
+    pub fn from_items(items: &[(&str, &str, &str)]) -> Self {
+        let items = items
+            .iter()
+            .map(|(title, url, description)| SearchItem {
+                title: title.to_string(),
+                url: url.to_string(),
+                description: description.to_string(),
+            })
+            .collect();
+        SearchResult { error: None, status: Some(200), index: 0, items: Some(items) }
     }
 }
 
@@ -168,9 +219,32 @@ impl SearchErrorResponse {
  *  curl -s --compressed "https://api.search.brave.com/res/v1/web/search?q=brave+search"\
     -H "Accept: application/json"\
     -H "Accept-Encoding: gzip"\
-	-H "X-Subscription-Token: BSA4als3AeUnLwszMAQalx8N2azrl1S")
+	-H "X-Subscription-Token: <BRAVE_SEARCH_API_KEY>")
  */
-pub fn search_brave(query: &str) -> Result<SearchResult, String> {
+/// Search Brave for `query`; `count` caps the number of results (Brave allows up to 20)
+pub async fn search_brave(query: String, count: Option<u32>) -> Result<SearchResult, String> {
+    let mut span = qvault_log::Span::start("search", "brave search");
+    span.field("provider", "brave");
+    span.field("query", qvault_log::query_for_log(&query));
+
+    let result = request_brave(&query, count, &mut span).await;
+    match &result {
+        Ok(res) => match res.error {
+            Some(ref err) => {
+                span.field("http_status", res.status.unwrap_or(0));
+                span.fail(err);
+            }
+            None => {
+                span.field("result_count", res.count());
+                span.finish();
+            }
+        },
+        Err(err) => span.fail(err),
+    }
+    result
+}
+
+async fn request_brave(query: &str, count: Option<u32>, span: &mut qvault_log::Span) -> Result<SearchResult, String> {
     // Check if query is empty and return an error in JSON
     if query.trim().is_empty() {
         return Ok(SearchResult {
@@ -181,7 +255,7 @@ pub fn search_brave(query: &str) -> Result<SearchResult, String> {
         });
     }
 
-    log_info("Doing brave search for query >>", format_args!("{}", query));
+    qvault_log::log_query("Doing brave search for query >>", query);
     let mut api_key = env::var("BRAVE_SEARCH_API_KEY").ok();
 
     if api_key.is_none() {
@@ -191,8 +265,12 @@ pub fn search_brave(query: &str) -> Result<SearchResult, String> {
             api_key = env::var("BRAVE_SEARCH_API_KEY").ok();  // Retry fetching the API key
         }
     }
+    if let Some(ref key) = api_key {
+        qvault_log::register_secret(key);
+    }
 
     if api_key.is_none() {
+        qvault_log::log_warn("Brave search attempted without BRAVE_SEARCH_API_KEY".to_string());
         return Ok(SearchResult {
             error: Some("API key not found. Please set the 'BRAVE_SEARCH_API_KEY' environment variable or provide it in qvault.env.".to_string()),
             status: Some(401),
@@ -202,20 +280,25 @@ pub fn search_brave(query: &str) -> Result<SearchResult, String> {
     }
 
     let url = "https://api.search.brave.com/res/v1/web/search";
-    let params = [("q", query)];
+    let mut params = vec![("q", query.to_string())];
+    if let Some(n) = count {
+        params.push(("count", n.to_string()));
+    }
 
-    let client = reqwest::blocking::Client::new();
+    let client = reqwest::Client::new();
     let response = client
         .get(url)
         .header("Accept", "application/json")
         .header("X-Subscription-Token", api_key.unwrap())
         .query(&params)
-        .send();
+        .send()
+        .await;
 
         match response {
             Ok(resp) => {
+                span.field("http_status", resp.status().as_u16());
                 if resp.status().is_success() {
-                    match resp.text() {
+                    match resp.text().await {
                         Ok(body) => {
                             /*
                             println!("Raw Body with Escapes: {:?}", body);
@@ -229,17 +312,13 @@ pub fn search_brave(query: &str) -> Result<SearchResult, String> {
                             // Attempt to parse as JSON
                             match serde_json::from_str::<serde_json::Value>(&body) {
                                 Ok(json) => {
-                                    log_info("Parsed JSON: ",format_args!("abc {}", json));
-
                                     // Navigate to the "web.results" array
                                     let results = json["web"]["results"].as_array().ok_or("Invalid results format")?;
-                                    log_info("VCVC Found results in JSON number:", format_args!("{}", results.len()));
-                                    log_info("VCVC Title of first result ", format_args!("{}", results[0]["title"]));
+                                    log_info("Found results in JSON number:", format_args!("{}", results.len()));
 
                                     let mut silist = SearchItemList::new();
                                     for res in results {
-                                        silist.add_search_item(res["title"].clone(), res["url"].clone());
-                                        log_info("VCVC Res: ", format_args!("title:{}", res["title"]));
+                                        silist.add_search_item(&res["title"], &res["url"], &res["description"]);
                                     }
                                     // Perform operations to extract the required data from `json`.
                                     let search_result = SearchResult {
@@ -253,7 +332,7 @@ pub fn search_brave(query: &str) -> Result<SearchResult, String> {
 
                                 }
                                 Err(err) => {
-                                    eprintln!("Failed to parse JSON: {}", err);
+                                    qvault_log::log_error(format!("Failed to parse JSON: {}", err));
                                     Err(format!("JSON parsing error: {}", err)) // Return an error wrapped in `Err`
                                 }
                             }
@@ -287,17 +366,19 @@ pub fn search_brave(query: &str) -> Result<SearchResult, String> {
 */
                         }
                         Err(read_err) => {
-                            eprintln!("Failed to read response body: {}", read_err);
+                            qvault_log::log_error(format!("Failed to read response body: {}", read_err));
                             Err("Failed to read response body.".to_string())
                         }
                     }
                 } else {
-                    eprintln!("HTTP Error: {}", resp.status());
+                    qvault_log::log_error(format!("HTTP Error: {}", resp.status()));
                     Err(format!("HTTP Error: {}", resp.status()))
                 }
             }
             Err(e) => {
-                eprintln!("Request Error: {}", e);
+                // The URL carries the query string, keep it out of the log
+                let e = e.without_url();
+                qvault_log::log_error(format!("Request Error: {}", e));
                 Err(format!("Request Error: {}", e))
             }
         }
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::qvault_log;
+
+/// Global config path, next to qvault.env and the history database
+pub static CONFIG_PATH: &str = "qvault.toml";
+
+/// User settings from qvault.toml. Every section is optional.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct QvaultConfig {
+    /// `/gh ripgrep` -> `/search site:github.com ripgrep`
+    pub aliases: BTreeMap<String, String>,
+    /// Like aliases, with `$1`..`$9` and `$*` filled in from the arguments
+    pub macros: BTreeMap<String, String>,
+    /// `!name` shortcuts: a URL template with `{}` for the query, or a
+    /// filter such as `site:docs.rs` put in front of the search
+    pub bangs: BTreeMap<String, String>,
+    pub pager: PagerConfig,
+    /// Color theme: dark, light, high-contrast or one from `[themes]`
+    pub theme: Option<String>,
+    /// `[themes.NAME]`: `base = "light"` plus colors to override, e.g.
+    /// `bar_bg = "#1e1e2e"`
+    pub themes: BTreeMap<String, BTreeMap<String, String>>,
+    pub display: DisplayConfig,
+    pub keys: KeysConfig,
+
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// Why the file could not be read. Saving would replace it with the
+    /// defaults, so save() refuses while this is set.
+    #[serde(skip)]
+    load_error: Option<String>,
+}
+
+/// `[pager]`: the search result pager
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PagerConfig {
+    /// Program for the `o` key, the URL is added as the last argument.
+    /// Defaults to xdg-open, or open on macOS.
+    pub opener: Option<String>,
+}
+
+/// `[keys]`: key bindings of the pager and scrolling views
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct KeysConfig {
+    /// default, vim or emacs
+    pub preset: Option<String>,
+    /// `[keys.bind]`: `quit = ["q", "C-g"]` replaces the preset's keys for quit
+    pub bind: BTreeMap<String, Vec<String>>,
+}
+
+/// `[display]`: accessibility settings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Off gives terminal default colors, like NO_COLOR
+    pub color: bool,
+    /// Plain ASCII instead of emoji and line drawing characters
+    pub ascii: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig { color: true, ascii: false }
+    }
+}
+
+impl QvaultConfig {
+    /// Read the config file. A missing file gives the defaults.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mut config = match fs::read_to_string(path) {
+            Ok(text) => toml::from_str::<QvaultConfig>(&text)
+                .map_err(|e| format!("{}: {}", path, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => QvaultConfig::default(),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+        config.path = Some(PathBuf::from(path));
+        Ok(config)
+    }
+
+    /// Like load, but a broken file is logged and replaced by the defaults
+    /// in memory, so a typo never keeps qvault from starting. The file is
+    /// left alone until it is fixed, see load_error.
+    pub fn load_or_default(path: &str) -> Self {
+        match Self::load(path) {
+            Ok(config) => config,
+            Err(err) => {
+                qvault_log::log_error(format!("Ignoring config: {}", err));
+                QvaultConfig { path: Some(PathBuf::from(path)), load_error: Some(err), ..Default::default() }
+            }
+        }
+    }
+
+    /// Error of the file load_or_default fell back from
+    pub fn load_error(&self) -> Option<&str> {
+        self.load_error.as_deref()
+    }
+
+    /// Write the config back to the file it was loaded from. Refused when
+    /// the file did not parse, it would lose everything in it.
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(err) = &self.load_error {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("it did not load, fix it first ({})", err)));
+        }
+        let path = self.path.as_deref().unwrap_or(Path::new(CONFIG_PATH));
+        let text = toml::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trip() {
+        let path = std::env::temp_dir().join(format!("qvault_config_{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut config = QvaultConfig::load(path).unwrap();
+        assert!(config.aliases.is_empty());
+        config.aliases.insert("gh".to_string(), "/search site:github.com".to_string());
+        config.macros.insert("rfc".to_string(), "/search \"RFC $1\" site:ietf.org".to_string());
+        config.theme = Some("mine".to_string());
+        config.themes.entry("mine".to_string()).or_default().insert("bar_bg".to_string(), "236".to_string());
+        config.save().unwrap();
+
+        let loaded = QvaultConfig::load(path).unwrap();
+        assert_eq!(loaded.aliases["gh"], "/search site:github.com");
+        assert_eq!(loaded.macros["rfc"], "/search \"RFC $1\" site:ietf.org");
+        assert_eq!(loaded.theme.as_deref(), Some("mine"));
+        assert_eq!(loaded.themes["mine"]["bar_bg"], "236");
+
+        fs::write(path, "aliases = 3").unwrap();
+        assert!(QvaultConfig::load(path).is_err());
+        let broken = QvaultConfig::load_or_default(path);
+        assert!(broken.aliases.is_empty());
+        assert!(broken.load_error().unwrap().starts_with(path));
+        assert!(broken.save().is_err());
+        assert_eq!(fs::read_to_string(path).unwrap(), "aliases = 3");
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,6 +1,5 @@
-use sled::{Db, IVec};
+use sled::Db;
 use std::str;
-use chrono::{DateTime, Utc};
 
 #[derive(Clone)]
 pub struct QvaultHistory {
@@ -8,12 +7,12 @@ pub struct QvaultHistory {
 }
 
 /// Global database path
-static DB_PATH: &str = "qvault_history.db";
+pub static DB_PATH: &str = "qvault_history.db";
 
 impl QvaultHistory {
     /// Opens or creates a database file
-    pub fn new() -> Result<Self, sled::Error> {
-        let db = sled::open(DB_PATH)?;
+    pub fn new(path: &str) -> Result<Self, sled::Error> {
+        let db = sled::open(path)?;
         Ok(Self {
             db
         })
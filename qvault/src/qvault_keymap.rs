@@ -0,0 +1,259 @@
+use termion::event::Key;
+
+use crate::qvault_config::QvaultConfig;
+
+/// Presets for `[keys] preset`
+pub const PRESETS: &[&str] = &["default", "vim", "emacs"];
+
+/// What a key does in the result pager and the scrolling views
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Next,
+    Prev,
+    /// To the top, or the first result
+    First,
+    Last,
+    PageDown,
+    PageUp,
+    /// Open the current URL in the browser
+    Open,
+    CopyUrl,
+    /// Copy `[title](url)`
+    CopyMarkdown,
+    Quit,
+}
+
+/// Every action with its config name, in /help order
+const ACTIONS: &[(Action, &str)] = &[
+    (Action::Next, "next"),
+    (Action::Prev, "prev"),
+    (Action::First, "first"),
+    (Action::Last, "last"),
+    (Action::PageDown, "page_down"),
+    (Action::PageUp, "page_up"),
+    (Action::Open, "open"),
+    (Action::CopyUrl, "copy_url"),
+    (Action::CopyMarkdown, "copy_markdown"),
+    (Action::Quit, "quit"),
+];
+
+impl Action {
+    fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(_, n)| *n == name).map(|(a, _)| *a)
+    }
+}
+
+/// Keys bound to actions. The first binding of an action is the one
+/// shown in hints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    pub preset: String,
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset("default").unwrap_or_else(|| Keymap { preset: String::new(), bindings: Vec::new() })
+    }
+}
+
+impl Keymap {
+    pub fn preset(name: &str) -> Option<Keymap> {
+        let keys: &[(Action, &[&str])] = match name {
+            "default" => &[
+                (Action::Next, &["Down", "j"]),
+                (Action::Prev, &["Up", "k"]),
+                (Action::First, &["Home", "g"]),
+                (Action::Last, &["End", "G"]),
+                (Action::PageDown, &["PageDown", "Space"]),
+                (Action::PageUp, &["PageUp"]),
+                (Action::Open, &["Enter", "o"]),
+                (Action::CopyUrl, &["y"]),
+                (Action::CopyMarkdown, &["Y"]),
+                (Action::Quit, &["q", "Esc"]),
+            ],
+            "vim" => &[
+                (Action::Next, &["j", "Down"]),
+                (Action::Prev, &["k", "Up"]),
+                (Action::First, &["g", "Home"]),
+                (Action::Last, &["G", "End"]),
+                (Action::PageDown, &["C-f", "C-d", "Space", "PageDown"]),
+                (Action::PageUp, &["C-b", "C-u", "PageUp"]),
+                (Action::Open, &["Enter", "o"]),
+                (Action::CopyUrl, &["y"]),
+                (Action::CopyMarkdown, &["Y"]),
+                (Action::Quit, &["q", "Esc"]),
+            ],
+            "emacs" => &[
+                (Action::Next, &["C-n", "Down"]),
+                (Action::Prev, &["C-p", "Up"]),
+                (Action::First, &["M-<", "Home"]),
+                (Action::Last, &["M->", "End"]),
+                (Action::PageDown, &["C-v", "PageDown"]),
+                (Action::PageUp, &["M-v", "PageUp"]),
+                (Action::Open, &["Enter"]),
+                (Action::CopyUrl, &["M-w"]),
+                (Action::CopyMarkdown, &["M-W"]),
+                (Action::Quit, &["C-g", "q", "Esc"]),
+            ],
+            _ => return None,
+        };
+        let mut bindings = Vec::new();
+        for (action, names) in keys {
+            bindings.extend(names.iter().filter_map(|n| parse_key(n).ok()).map(|k| (k, *action)));
+        }
+        Some(Keymap { preset: name.to_string(), bindings })
+    }
+
+    /// `[keys] preset`, then each action listed in `[keys.bind]` replaces
+    /// the preset's keys for that action
+    pub fn from_config(config: &QvaultConfig) -> Result<Keymap, String> {
+        let preset = config.keys.preset.as_deref().unwrap_or("default");
+        let mut keymap = Keymap::preset(preset)
+            .ok_or_else(|| format!("No key preset named {}, try one of {}", preset, PRESETS.join(", ")))?;
+
+        for (name, keys) in &config.keys.bind {
+            let action = Action::from_name(name).ok_or_else(|| {
+                let names: Vec<&str> = ACTIONS.iter().map(|(_, n)| *n).collect();
+                format!("Unknown key action {}, try one of {}", name, names.join(", "))
+            })?;
+            let keys = keys.iter().map(|k| parse_key(k)).collect::<Result<Vec<Key>, String>>()?;
+            keymap.bindings.retain(|(key, a)| *a != action && !keys.contains(key));
+            keymap.bindings.extend(keys.into_iter().map(|k| (k, action)));
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings.iter().find(|(k, _)| *k == key).map(|(_, a)| *a)
+    }
+
+    pub fn keys(&self, action: Action) -> Vec<Key> {
+        self.bindings.iter().filter(|(_, a)| *a == action).map(|(k, _)| *k).collect()
+    }
+
+    /// Name of the first key bound to `action`, for hints like "j/k move"
+    pub fn hint(&self, action: Action) -> String {
+        self.keys(action).first().map_or("-".to_string(), |k| key_name(*k))
+    }
+
+    /// Movement keys of the scrolling views, for the status bar
+    pub fn scroll_hint(&self) -> String {
+        format!(
+            "{}/{} scroll  {}/{} page  {}/{} top/end  {} done",
+            self.hint(Action::Next),
+            self.hint(Action::Prev),
+            self.hint(Action::PageDown),
+            self.hint(Action::PageUp),
+            self.hint(Action::First),
+            self.hint(Action::Last),
+            self.hint(Action::Quit)
+        )
+    }
+
+    /// One line per action, for /help
+    pub fn describe(&self) -> Vec<String> {
+        ACTIONS
+            .iter()
+            .map(|(action, name)| {
+                let keys: Vec<String> = self.keys(*action).into_iter().map(key_name).collect();
+                format!("{:<14} {}", name, keys.join(", "))
+            })
+            .collect()
+    }
+}
+
+/// Key names as written in config: a character, `C-x` for Ctrl, `M-x` for
+/// Alt, or one of Enter, Tab, Space, Esc, Backspace, Up, Down, Left, Right,
+/// Home, End, PageUp, PageDown, BackTab, Delete
+pub fn parse_key(name: &str) -> Result<Key, String> {
+    let single = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+    if let Some(c) = name.strip_prefix("C-").and_then(single) {
+        return Ok(Key::Ctrl(c.to_ascii_lowercase()));
+    }
+    if let Some(c) = name.strip_prefix("M-").and_then(single) {
+        return Ok(Key::Alt(c));
+    }
+    if let Some(c) = single(name) {
+        return Ok(Key::Char(c));
+    }
+    Ok(match name.to_lowercase().as_str() {
+        "enter" | "return" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        "esc" | "escape" => Key::Esc,
+        "backspace" => Key::Backspace,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" | "pgup" => Key::PageUp,
+        "pagedown" | "pgdn" => Key::PageDown,
+        "backtab" => Key::BackTab,
+        "delete" | "del" => Key::Delete,
+        _ => return Err(format!("Unknown key {}", name)),
+    })
+}
+
+/// Inverse of parse_key
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char('\n') => "Enter".to_string(),
+        Key::Char('\t') => "Tab".to_string(),
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("C-{}", c),
+        Key::Alt(c) => format!("M-{}", c),
+        Key::Esc => "Esc".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        Key::Up => "Up".to_string(),
+        Key::Down => "Down".to_string(),
+        Key::Left => "Left".to_string(),
+        Key::Right => "Right".to_string(),
+        Key::Home => "Home".to_string(),
+        Key::End => "End".to_string(),
+        Key::PageUp => "PageUp".to_string(),
+        Key::PageDown => "PageDown".to_string(),
+        Key::BackTab => "BackTab".to_string(),
+        Key::Delete => "Delete".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap_from_config() {
+        let mut config = QvaultConfig::default();
+        let keymap = Keymap::from_config(&config).unwrap();
+        assert_eq!(keymap.action(Key::Char('j')), Some(Action::Next));
+        assert_eq!(keymap.action(Key::Char('\t')), None);
+        assert_eq!(keymap.hint(Action::Open), "Enter");
+
+        config.keys.preset = Some("emacs".to_string());
+        config.keys.bind.insert("quit".to_string(), vec!["Tab".to_string(), "C-G".to_string()]);
+        let keymap = Keymap::from_config(&config).unwrap();
+        assert_eq!(keymap.action(Key::Ctrl('n')), Some(Action::Next));
+        assert_eq!(keymap.action(Key::Alt('v')), Some(Action::PageUp));
+        assert_eq!(keymap.action(Key::Char('j')), None);
+        assert_eq!(keymap.keys(Action::Quit), [Key::Char('\t'), Key::Ctrl('g')]);
+        assert_eq!(keymap.describe()[0], "next           C-n, Down");
+
+        config.keys.bind.insert("jump".to_string(), vec!["x".to_string()]);
+        assert!(Keymap::from_config(&config).unwrap_err().starts_with("Unknown key action jump"));
+        assert_eq!(parse_key("Hyper-x"), Err("Unknown key Hyper-x".to_string()));
+        for name in ["PageDown", "M-<", "C-f", "Space", "Y"] {
+            assert_eq!(key_name(parse_key(name).unwrap()), name);
+        }
+    }
+}
@@ -1,16 +1,52 @@
 use log::{Level, Metadata, Record};
 use std::fs::{File, OpenOptions};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::io::{Write, BufWriter};
+use std::time::Instant;
 use once_cell::sync::Lazy; // For lazy initialization
 use chrono::Utc;
 use log::Log;
+use regex::Regex;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
 use std::fmt::Arguments;
 
+/// Replacement text for anything that looks like a secret
+const REDACTED: &str = "[REDACTED]";
+
+/// Secret values shorter than this are not registered, they would mask ordinary words
+const MIN_SECRET_LEN: usize = 8;
+
+/// Where the file logger writes
+pub const LOG_PATH: &str = "/var/log/qvault.log";
+
+/// Structured key/value pairs attached to a log event
+pub type Fields = Vec<(&'static str, Value)>;
+
 struct FileLogger {
     file: Option<Mutex<BufWriter<File>>>, // Use Option to safely allow closing the file
 }
 
+impl FileLogger {
+    fn write_event(&self, level: Level, module: &str, message: &str, fields: &[(&'static str, Value)]) {
+        if let Some(file_mutex) = self.file.as_ref() { // Borrow the file safely
+            if let Ok(mut file) = file_mutex.lock() {
+                let log_entry = format_entry(
+                    is_json_mode(),
+                    &Utc::now().to_rfc3339(),
+                    level,
+                    module,
+                    message,
+                    fields,
+                );
+                let _ = file.write_all(log_entry.as_bytes());
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
 impl log::Log for FileLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
         metadata.level() <= Level::Info
@@ -18,23 +54,12 @@ impl log::Log for FileLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            if let Some(ref file_mutex) = self.file.as_ref() { // Borrow the file safely
-                if let Ok(mut file) = file_mutex.lock() {
-                    let log_entry = format!(
-                        "{} - [{}] {}\n",
-                        Utc::now().to_rfc3339(),
-                        record.level(),
-                        record.args()
-                    );
-                    let _ = file.write_all(log_entry.as_bytes());
-                    let _ = file.flush();
-                }
-            }
+            self.write_event(record.level(), record.target(), &record.args().to_string(), &[]);
         }
     }
 
     fn flush(&self) {
-        if let Some(ref file_mutex) = self.file.as_ref() { // Safely borrow
+        if let Some(file_mutex) = self.file.as_ref() { // Safely borrow
             if let Ok(mut file) = file_mutex.lock() {
                 let _ = file.flush();
             }
@@ -44,7 +69,7 @@ impl log::Log for FileLogger {
 
 impl Drop for FileLogger {
     fn drop(&mut self) {
-        if let Some(ref file_mutex) = self.file.as_ref() {
+        if let Some(file_mutex) = self.file.as_ref() {
             if let Ok(mut file) = file_mutex.lock() {
                 let _ = file.flush();
             }
@@ -58,15 +83,44 @@ static LOGGER: Lazy<FileLogger> = Lazy::new(|| {
     let file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open("/var/log/qvault.log")
+        .open(LOG_PATH)
         .expect("Failed to open log file");
     FileLogger {
         file: Some(Mutex::new(BufWriter::new(file))),
     }
 });
 
+/// Known secret shapes: provider keys, bearer tokens and `key=value` style assignments.
+/// Assignments keep their name so the log still says which setting was involved.
+static SECRET_PATTERNS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
+    vec![
+        (Regex::new(r"sk-[A-Za-z0-9_\-]{16,}").unwrap(), REDACTED),
+        (Regex::new(r"BSA[A-Za-z0-9_\-]{16,}").unwrap(), REDACTED),
+        (Regex::new(r"(?i)(bearer\s+)[A-Za-z0-9._\-]+").unwrap(), "${1}[REDACTED]"),
+        (
+            Regex::new(r#"(?i)((?:api[_\-]?key|token|secret|password|authorization)["']?\s*[:=]\s*["']?)[^\s"',}]+"#).unwrap(),
+            "${1}[REDACTED]",
+        ),
+    ]
+});
+
+/// Key values loaded at runtime (env, qvault.env, settings form)
+static SECRETS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// When set, query text is hashed before it reaches the log
+static PRIVATE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// When set, every event is written as one JSON object per line
+static JSON_MODE: AtomicBool = AtomicBool::new(false);
+
 /// Initialize the file logger
 pub fn init_log() {
+    if let Ok(val) = std::env::var("QVAULT_LOG_PRIVATE") {
+        set_private_mode(matches!(val.to_lowercase().as_str(), "1" | "true" | "yes" | "on"));
+    }
+    if let Ok(val) = std::env::var("QVAULT_LOG_FORMAT") {
+        set_json_mode(val.eq_ignore_ascii_case("json"));
+    }
     log::set_logger(&*LOGGER)
         .map(|()| log::set_max_level(log::LevelFilter::Info))
         .expect("Failed to set logger");
@@ -74,7 +128,7 @@ pub fn init_log() {
 
 /// Shutdown and flush the logs before exiting
 pub fn shutdown_log() {
-    if let Some(ref file_mutex) = LOGGER.file.as_ref() {
+    if let Some(file_mutex) = LOGGER.file.as_ref() {
         if let Ok(mut file) = file_mutex.lock() {
             let _ = file.flush();
             println!("Shutdown and flushed log data.");
@@ -82,6 +136,213 @@ pub fn shutdown_log() {
     }
 }
 
+/// Remember a configured key so it is masked wherever it shows up in the log
+pub fn register_secret(value: &str) {
+    let value = value.trim();
+    if value.len() < MIN_SECRET_LEN {
+        return;
+    }
+    if let Ok(mut secrets) = SECRETS.lock() {
+        if !secrets.iter().any(|s| s == value) {
+            secrets.push(value.to_string());
+        }
+    }
+}
+
+/// Mask registered key values and anything matching a known secret pattern
+pub fn redact(text: &str) -> String {
+    let mut out = text.to_string();
+    if let Ok(secrets) = SECRETS.lock() {
+        for secret in secrets.iter() {
+            out = out.replace(secret.as_str(), REDACTED);
+        }
+    }
+    for (re, replacement) in SECRET_PATTERNS.iter() {
+        out = re.replace_all(&out, *replacement).into_owned();
+    }
+    out
+}
+
+/// Mask a key for display, keeping only the last four characters
+pub fn mask_secret(value: &str) -> String {
+    let count = value.chars().count();
+    if count <= 4 {
+        return "*".repeat(count);
+    }
+    let tail: String = value.chars().skip(count - 4).collect();
+    format!("{}{}", "*".repeat(count - 4), tail)
+}
+
+pub fn set_private_mode(on: bool) {
+    PRIVATE_MODE.store(on, Ordering::Relaxed);
+}
+
+pub fn is_private_mode() -> bool {
+    PRIVATE_MODE.load(Ordering::Relaxed)
+}
+
+/// Query text as it should appear in the log. In private mode this is a short
+/// stable hash, so support can still correlate repeated queries without reading them.
+pub fn query_for_log(query: &str) -> String {
+    if !is_private_mode() {
+        return query.to_string();
+    }
+    let digest = Sha256::digest(query.as_bytes());
+    let hex: String = digest.iter().take(6).map(|b| format!("{:02x}", b)).collect();
+    format!("<query:{}>", hex)
+}
+
+pub fn set_json_mode(on: bool) {
+    JSON_MODE.store(on, Ordering::Relaxed);
+}
+
+pub fn is_json_mode() -> bool {
+    JSON_MODE.load(Ordering::Relaxed)
+}
+
+/// Keys every JSON log line has. A field with one of these names is
+/// written as `field.<name>` so it cannot replace the real value.
+const RESERVED_KEYS: [&str; 4] = ["timestamp", "level", "module", "message"];
+
+/// Render one log line. Text mode appends fields as `key=value`,
+/// JSON mode emits a single object with the fields at top level.
+fn format_entry(
+    json: bool,
+    timestamp: &str,
+    level: Level,
+    module: &str,
+    message: &str,
+    fields: &[(&'static str, Value)],
+) -> String {
+    let message = redact(message);
+    let fields = fields.iter().map(|(k, v)| {
+        let v = match v {
+            Value::String(s) => Value::String(redact(s)),
+            other => other.clone(),
+        };
+        (*k, v)
+    });
+
+    if json {
+        let mut obj = Map::new();
+        obj.insert("timestamp".to_string(), Value::from(timestamp));
+        obj.insert("level".to_string(), Value::from(level.to_string()));
+        obj.insert("module".to_string(), Value::from(module));
+        obj.insert("message".to_string(), Value::from(message));
+        for (k, v) in fields {
+            let key = if RESERVED_KEYS.contains(&k) { format!("field.{}", k) } else { k.to_string() };
+            obj.insert(key, v);
+        }
+        format!("{}\n", Value::Object(obj))
+    } else {
+        let mut entry = format!("{} - [{}] {}", timestamp, level, message);
+        for (k, v) in fields {
+            match v {
+                Value::String(s) => entry.push_str(&format!(" {}={:?}", k, s)),
+                other => entry.push_str(&format!(" {}={}", k, other)),
+            }
+        }
+        entry.push('\n');
+        entry
+    }
+}
+
+/// Log an event with structured fields such as provider, status or latency
+pub fn log_event(level: Level, module: &str, message: &str, fields: Fields) {
+    if level <= log::max_level() {
+        LOGGER.write_event(level, module, message, &fields);
+    }
+}
+
+/// Times one provider request. Fields gathered along the way are written
+/// together with `latency_ms` when the span finishes.
+pub struct Span {
+    module: &'static str,
+    name: &'static str,
+    start: Instant,
+    fields: Fields,
+}
+
+impl Span {
+    pub fn start(module: &'static str, name: &'static str) -> Self {
+        Span { module, name, start: Instant::now(), fields: Vec::new() }
+    }
+
+    pub fn field(&mut self, key: &'static str, value: impl Into<Value>) {
+        self.fields.push((key, value.into()));
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    /// Log the span as successful
+    pub fn finish(self) {
+        self.emit(Level::Info, None);
+    }
+
+    /// Log the span as failed with the given error text
+    pub fn fail(self, error: &str) {
+        self.emit(Level::Error, Some(error));
+    }
+
+    fn emit(mut self, level: Level, error: Option<&str>) {
+        let latency = self.elapsed_ms();
+        self.fields.push(("latency_ms", Value::from(latency)));
+        if let Some(err) = error {
+            self.fields.push(("error", Value::from(err)));
+        }
+        let message = format!("{} {}", self.name, if error.is_some() { "failed" } else { "done" });
+        log_event(level, self.module, &message, std::mem::take(&mut self.fields));
+    }
+}
+
+/// Level of a line already written to the log, in either text or JSON format
+pub fn line_level(line: &str) -> Option<Level> {
+    static LEVEL_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r#"\[(ERROR|WARN|INFO|DEBUG|TRACE)\]|"level":"(ERROR|WARN|INFO|DEBUG|TRACE)""#).unwrap()
+    });
+    let caps = LEVEL_RE.captures(line)?;
+    let name = caps.get(1).or_else(|| caps.get(2))?.as_str();
+    name.parse().ok()
+}
+
+/// Which log lines the /log viewer shows
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    pub min_level: Level,
+    pub needle: String,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        LogFilter { min_level: Level::Trace, needle: String::new() }
+    }
+}
+
+impl LogFilter {
+    /// Lines without a recognisable level (wrapped output, panics) are kept
+    pub fn matches(&self, line: &str) -> bool {
+        if let Some(level) = line_level(line) {
+            if level > self.min_level {
+                return false;
+            }
+        }
+        self.needle.is_empty() || line.to_lowercase().contains(&self.needle.to_lowercase())
+    }
+
+    /// Step through ERROR -> WARN -> INFO -> DEBUG -> TRACE and back
+    pub fn cycle_level(&mut self) {
+        self.min_level = match self.min_level {
+            Level::Error => Level::Warn,
+            Level::Warn => Level::Info,
+            Level::Info => Level::Debug,
+            Level::Debug => Level::Trace,
+            Level::Trace => Level::Error,
+        };
+    }
+}
+
 pub fn log_info(message: &str, args: Arguments) {
     let formatted_message = format!("{}{}", message, args); // Convert Arguments to string
     // Assuming logging to a file or stdout
@@ -89,6 +350,10 @@ pub fn log_info(message: &str, args: Arguments) {
     LOGGER.flush();
 }
 
+/// Log user supplied query text, hashed when private mode is on
+pub fn log_query(message: &str, query: &str) {
+    log_info(message, format_args!("{}", query_for_log(query)));
+}
 
 pub fn log_warn(message: String) {
     log::warn!("{}", message);
@@ -96,4 +361,83 @@ pub fn log_warn(message: String) {
 
 pub fn log_error(message: String) {
     log::error!("{}", message);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_patterns() {
+        let line = "Authorization: Bearer abc.def-123 key sk-abcdefghijklmnopqrstuv";
+        let out = redact(line);
+        assert!(!out.contains("abc.def-123"));
+        assert!(!out.contains("sk-abcdefghijklmnopqrstuv"));
+
+        let out = redact("BRAVE_SEARCH_API_KEY=BSAabcdefghijklmnopqrstu and api_key: hunter22");
+        assert!(!out.contains("BSAabcdefghijklmnopqrstu"));
+        assert!(!out.contains("hunter22"));
+        assert!(out.contains("api_key: [REDACTED]"));
+    }
+
+    #[test]
+    fn test_redact_registered_secret() {
+        register_secret("plain-looking-value-42");
+        register_secret("short");
+        assert_eq!(redact("got plain-looking-value-42 back"), "got [REDACTED] back");
+        assert_eq!(redact("short answer"), "short answer");
+    }
+
+    #[test]
+    fn test_format_entry() {
+        let fields: Fields = vec![("provider", Value::from("brave")), ("latency_ms", Value::from(42))];
+        let line = format_entry(true, "2024-01-01T00:00:00Z", Level::Info, "search", "search done", &fields);
+        let obj: Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(obj["level"], "INFO");
+        assert_eq!(obj["module"], "search");
+        assert_eq!(obj["provider"], "brave");
+        assert_eq!(obj["latency_ms"], 42);
+
+        // Fields named like the fixed keys are prefixed instead of replacing them
+        let clash: Fields = vec![("message", Value::from("from span")), ("level", Value::from(3))];
+        let line = format_entry(true, "2024-01-01T00:00:00Z", Level::Info, "search", "search done", &clash);
+        let obj: Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(obj["message"], "search done");
+        assert_eq!(obj["level"], "INFO");
+        assert_eq!(obj["field.message"], "from span");
+        assert_eq!(obj["field.level"], 3);
+
+        let line = format_entry(false, "t", Level::Warn, "ai", "key sk-abcdefghijklmnopqrstuv", &fields);
+        assert_eq!(line, "t - [WARN] key [REDACTED] provider=\"brave\" latency_ms=42\n");
+    }
+
+    #[test]
+    fn test_log_filter() {
+        let text = "2024-01-01T00:00:00Z - [WARN] Brave search attempted without key";
+        let json = r#"{"level":"ERROR","message":"openai chat failed"}"#;
+        assert_eq!(line_level(text), Some(Level::Warn));
+        assert_eq!(line_level(json), Some(Level::Error));
+
+        let mut filter = LogFilter { min_level: Level::Error, needle: String::new() };
+        assert!(!filter.matches(text));
+        assert!(filter.matches(json));
+        filter.cycle_level();
+        assert!(filter.matches(text));
+        filter.needle = "OPENAI".to_string();
+        assert!(!filter.matches(text));
+        assert!(filter.matches(json));
+        assert!(filter.matches("thread 'main' panicked at openai"));
+    }
+
+    #[test]
+    fn test_private_query_hash() {
+        assert_eq!(mask_secret("abcdefgh"), "****efgh");
+        set_private_mode(true);
+        let a = query_for_log("rust async traits");
+        assert!(a.starts_with("<query:"));
+        assert!(!a.contains("rust"));
+        assert_eq!(a, query_for_log("rust async traits"));
+        set_private_mode(false);
+        assert_eq!(query_for_log("rust"), "rust");
+    }
+}
@@ -0,0 +1,658 @@
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::qvault_theme::Theme;
+
+/// Style of link text. The OSC 8 overlay writes links the same way.
+pub fn link_style(theme: &Theme) -> Style {
+    Style::default().fg(theme.url).add_modifier(Modifier::UNDERLINED)
+}
+
+/// Markdown laid out as terminal rows
+#[derive(Debug, Default)]
+pub struct Rendered {
+    pub lines: Vec<Spans<'static>>,
+    pub links: Vec<LinkSpan>,
+}
+
+/// Where the text of a link ended up, so it can be made clickable
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkSpan {
+    pub row: usize,
+    pub col: usize,
+    pub text: String,
+    pub url: String,
+}
+
+/// A code block of a markdown text, numbered from 1 in the rendered output
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    pub lang: String,
+    pub code: String,
+}
+
+/// Inline text sharing one style
+#[derive(Debug, Clone)]
+struct Run {
+    text: String,
+    style: Style,
+    url: Option<String>,
+}
+
+/// One open list; `next` is the number of the next item of an ordered list
+struct List {
+    next: Option<u64>,
+    indent: usize,
+}
+
+/// Table cells collected until the table ends and column widths are known
+struct Table {
+    aligns: Vec<Alignment>,
+    /// Header first
+    rows: Vec<Vec<String>>,
+    cell: String,
+}
+
+struct Renderer<'t> {
+    width: usize,
+    theme: &'t Theme,
+    out: Rendered,
+    runs: Vec<Run>,
+    bold: usize,
+    italic: usize,
+    strike: usize,
+    heading: Option<HeadingLevel>,
+    link: Option<String>,
+    lists: Vec<List>,
+    /// Bullet or number waiting for the first row of a list item
+    marker: Option<String>,
+    quote: usize,
+    /// (language, text) of the fenced block being read
+    code: Option<(String, String)>,
+    /// Code blocks seen so far, for their labels
+    blocks: usize,
+    table: Option<Table>,
+}
+
+/// Lay out markdown for a pane `width` columns wide
+pub fn render(text: &str, width: usize, theme: &Theme) -> Rendered {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut r = Renderer {
+        width: width.max(1),
+        theme,
+        out: Rendered::default(),
+        runs: Vec::new(),
+        bold: 0,
+        italic: 0,
+        strike: 0,
+        heading: None,
+        link: None,
+        lists: Vec::new(),
+        marker: None,
+        quote: 0,
+        code: None,
+        blocks: 0,
+        table: None,
+    };
+    for event in Parser::new_ext(text, options) {
+        r.event(event);
+    }
+    r.flush();
+    r.out
+}
+
+/// Code blocks of a markdown text, in the order `render` numbers them
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<CodeBlock> = None;
+    for event in Parser::new_ext(text, Options::ENABLE_TABLES) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                current = Some(CodeBlock { lang, code: String::new() });
+            }
+            Event::Text(text) => {
+                if let Some(block) = current.as_mut() {
+                    block.code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => blocks.extend(current.take()),
+            _ => {}
+        }
+    }
+    blocks
+}
+
+impl Renderer<'_> {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if let Some((_, code)) = self.code.as_mut() {
+                    code.push_str(&text);
+                } else if let Some(table) = self.table.as_mut() {
+                    table.cell.push_str(&text);
+                } else {
+                    self.push(&text, self.style());
+                }
+            }
+            Event::Code(code) => {
+                if let Some(table) = self.table.as_mut() {
+                    table.cell.push_str(&code);
+                } else {
+                    self.push(&code, Style::default().fg(self.theme.code_fg).bg(self.theme.code_bg));
+                }
+            }
+            Event::Html(html) | Event::InlineHtml(html) => self.push(&html, Style::default().add_modifier(Modifier::DIM)),
+            Event::SoftBreak => match self.table.as_mut() {
+                Some(table) => table.cell.push(' '),
+                None => self.push(" ", self.style()),
+            },
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.gap();
+                let rule = self.theme.glyphs.rule.repeat(self.width.min(60));
+                self.out.lines.push(Spans::from(Span::styled(rule, Style::default().add_modifier(Modifier::DIM))));
+            }
+            Event::TaskListMarker(done) => self.push(if done { "[x] " } else { "[ ] " }, self.style()),
+            Event::FootnoteReference(name) => self.push(&format!("[{}]", name), self.style()),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => {
+                self.flush();
+                self.gap();
+            }
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.gap();
+                self.heading = Some(level);
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.gap();
+                self.quote += 1;
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                self.gap();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((lang, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.gap();
+                }
+                self.lists.push(List { next: start, indent: 0 });
+            }
+            Tag::Item => {
+                self.flush();
+                let depth = self.lists.len();
+                if let Some(list) = self.lists.last_mut() {
+                    let marker = match list.next.as_mut() {
+                        Some(n) => {
+                            *n += 1;
+                            format!("{}. ", *n - 1)
+                        }
+                        None => self.theme.glyphs.list[(depth - 1) % 3].to_string(),
+                    };
+                    list.indent = marker.width();
+                    self.marker = Some(marker);
+                }
+            }
+            Tag::Table(aligns) => {
+                self.flush();
+                self.gap();
+                self.table = Some(Table { aligns, rows: Vec::new(), cell: String::new() });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = self.table.as_mut() {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::Emphasis => self.italic += 1,
+            Tag::Strong => self.bold += 1,
+            Tag::Strikethrough => self.strike += 1,
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => self.link = Some(dest_url.to_string()),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::Item => self.flush(),
+            TagEnd::Heading(_) => {
+                self.flush();
+                self.heading = None;
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.quote = self.quote.saturating_sub(1);
+            }
+            TagEnd::CodeBlock => {
+                if let Some((lang, code)) = self.code.take() {
+                    self.code_block(&lang, &code);
+                }
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+            }
+            TagEnd::TableCell => {
+                if let Some(table) = self.table.as_mut() {
+                    let cell = std::mem::take(&mut table.cell);
+                    if let Some(row) = table.rows.last_mut() {
+                        row.push(cell.trim().to_string());
+                    }
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.table_rows(&table);
+                }
+            }
+            TagEnd::Emphasis => self.italic = self.italic.saturating_sub(1),
+            TagEnd::Strong => self.bold = self.bold.saturating_sub(1),
+            TagEnd::Strikethrough => self.strike = self.strike.saturating_sub(1),
+            TagEnd::Link | TagEnd::Image => self.link = None,
+            _ => {}
+        }
+    }
+
+    /// Style for text at the current nesting of headings, emphasis and links
+    fn style(&self) -> Style {
+        let mut style = match self.heading {
+            Some(HeadingLevel::H1) => Style::default().fg(self.theme.title).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            Some(HeadingLevel::H2) => Style::default().fg(self.theme.title).add_modifier(Modifier::BOLD),
+            Some(_) => Style::default().add_modifier(Modifier::BOLD),
+            None => Style::default(),
+        };
+        if self.bold > 0 {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if self.italic > 0 {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if self.strike > 0 {
+            style = style.add_modifier(Modifier::CROSSED_OUT);
+        }
+        if self.link.is_some() {
+            style = style.patch(link_style(self.theme));
+        }
+        style
+    }
+
+    fn push(&mut self, text: &str, style: Style) {
+        self.runs.push(Run { text: text.to_string(), style, url: self.link.clone() });
+    }
+
+    /// Blank row between blocks, except at the top and inside lists
+    fn gap(&mut self) {
+        if self.lists.is_empty() && self.out.lines.last().is_some_and(|l| l.width() > self.quote * 2) {
+            let bars = self.theme.glyphs.quote.repeat(self.quote);
+            self.out.lines.push(Spans::from(Span::styled(bars, Style::default().add_modifier(Modifier::DIM))));
+        }
+    }
+
+    /// Quote bars and list indent in front of the next row. The first row
+    /// of a list item carries its bullet.
+    fn prefix(&mut self) -> String {
+        let mut prefix = self.theme.glyphs.quote.repeat(self.quote);
+        let indent: usize = self.lists.iter().map(|l| l.indent).sum();
+        match self.marker.take() {
+            Some(marker) => {
+                prefix.push_str(&" ".repeat(indent.saturating_sub(marker.width())));
+                prefix.push_str(&marker);
+            }
+            None => prefix.push_str(&" ".repeat(indent)),
+        }
+        prefix
+    }
+
+    fn push_row(&mut self, prefix: String, mut spans: Vec<Span<'static>>) {
+        spans.insert(0, Span::styled(prefix, Style::default().add_modifier(Modifier::DIM)));
+        self.out.lines.push(Spans::from(spans));
+    }
+
+    /// Word wrap the pending inline text into rows
+    fn flush(&mut self) {
+        if self.runs.is_empty() {
+            return;
+        }
+        let runs = std::mem::take(&mut self.runs);
+        let cells: Vec<(char, usize)> = runs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, run)| run.text.chars().map(move |c| (c, i)))
+            .collect();
+
+        let first = self.prefix();
+        let rest = self.prefix();
+        for (n, row) in wrap(&cells, self.width.saturating_sub(first.width())).into_iter().enumerate() {
+            let prefix = if n == 0 { first.clone() } else { rest.clone() };
+            let mut col = prefix.width();
+            let mut spans = Vec::new();
+            for group in row.chunk_by(|a, b| a.1 == b.1) {
+                let run = &runs[group[0].1];
+                let text: String = group.iter().map(|(c, _)| c).collect();
+                if let Some(url) = &run.url {
+                    self.out.links.push(LinkSpan { row: self.out.lines.len(), col, text: text.clone(), url: url.clone() });
+                }
+                col += text.width();
+                spans.push(Span::styled(text, run.style));
+            }
+            self.push_row(prefix, spans);
+        }
+    }
+
+    /// Fenced code on a shaded background, cut (not wrapped at words) at the
+    /// edge, under a `[n] lang` label for /copy and /save
+    fn code_block(&mut self, lang: &str, code: &str) {
+        self.blocks += 1;
+        let label = format!("[{}] {}", self.blocks, lang);
+        let first = self.prefix();
+        self.push_row(first, vec![Span::styled(label.trim_end().to_string(), Style::default().add_modifier(Modifier::DIM))]);
+        let rest = self.prefix();
+        let inner = self.width.saturating_sub(rest.width() + 2).max(1);
+        let bg = Style::default().bg(self.theme.code_bg);
+
+        let mut rows: Vec<Vec<Span<'static>>> = Vec::new();
+        for line in code.trim_end_matches('\n').split('\n') {
+            let runs = highlight(&line.replace('\t', "    "), lang, self.theme);
+            let mut row: Vec<Span<'static>> = Vec::new();
+            let mut used = 0;
+            for run in runs {
+                let mut piece = String::new();
+                for c in run.text.chars() {
+                    let w = c.width().unwrap_or(0);
+                    if used + w > inner {
+                        row.push(Span::styled(std::mem::take(&mut piece), run.style));
+                        rows.push(std::mem::take(&mut row));
+                        used = 0;
+                    }
+                    piece.push(c);
+                    used += w;
+                }
+                row.push(Span::styled(piece, run.style));
+            }
+            rows.push(row);
+        }
+
+        for mut row in rows {
+            let used: usize = row.iter().map(|s| s.width()).sum();
+            row.insert(0, Span::styled(" ", bg));
+            row.push(Span::styled(" ".repeat(inner.saturating_sub(used) + 1), bg));
+            self.push_row(rest.clone(), row);
+        }
+    }
+
+    /// Columns padded to the widest cell, shrunk to fit the pane
+    fn table_rows(&mut self, table: &Table) {
+        let prefix = self.prefix();
+        let columns = table.rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let mut widths: Vec<usize> = (0..columns)
+            .map(|c| table.rows.iter().filter_map(|r| r.get(c)).map(|s| s.width()).max().unwrap_or(0).max(1))
+            .collect();
+        let room = self.width.saturating_sub(prefix.width() + 3 * (columns - 1));
+        while widths.iter().sum::<usize>() > room {
+            match widths.iter_mut().filter(|w| **w > 3).max() {
+                Some(widest) => *widest -= 1,
+                None => break,
+            }
+        }
+
+        let dim = Style::default().add_modifier(Modifier::DIM);
+        let glyphs = self.theme.glyphs;
+        for (i, row) in table.rows.iter().enumerate() {
+            let style = if i == 0 { Style::default().add_modifier(Modifier::BOLD) } else { Style::default() };
+            let mut spans = Vec::new();
+            for (c, width) in widths.iter().enumerate() {
+                if c > 0 {
+                    spans.push(Span::styled(glyphs.column, dim));
+                }
+                let cell = row.get(c).map(String::as_str).unwrap_or("");
+                let align = table.aligns.get(c).copied().unwrap_or(Alignment::None);
+                spans.push(Span::styled(fit_cell(cell, *width, align, glyphs.ellipsis), style));
+            }
+            self.push_row(prefix.clone(), spans);
+
+            if i == 0 {
+                let rule: Vec<String> = widths.iter().map(|w| glyphs.rule.repeat(*w)).collect();
+                let rule = Span::styled(rule.join(glyphs.cross), dim);
+                self.push_row(prefix.clone(), vec![rule]);
+            }
+        }
+    }
+}
+
+/// Pad or cut a table cell to exactly `width` columns
+fn fit_cell(text: &str, width: usize, align: Alignment, ellipsis: &str) -> String {
+    let mut cell = String::new();
+    if text.width() > width {
+        for c in text.chars() {
+            if cell.width() + c.width().unwrap_or(0) + ellipsis.width() > width {
+                break;
+            }
+            cell.push(c);
+        }
+        cell.push_str(ellipsis);
+    } else {
+        cell.push_str(text);
+    }
+    let pad = width.saturating_sub(cell.width());
+    match align {
+        Alignment::Right => format!("{}{}", " ".repeat(pad), cell),
+        Alignment::Center => format!("{}{}{}", " ".repeat(pad / 2), cell, " ".repeat(pad - pad / 2)),
+        _ => format!("{}{}", cell, " ".repeat(pad)),
+    }
+}
+
+/// Comment marker and keywords of the languages we color
+fn syntax(lang: &str) -> Option<(&'static str, &'static [&'static str])> {
+    const RUST: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "fn", "for", "if", "impl",
+        "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct",
+        "super", "trait", "type", "unsafe", "use", "where", "while", "true", "false",
+    ];
+    const PYTHON: &[&str] = &[
+        "and", "as", "async", "await", "class", "def", "elif", "else", "except", "False", "finally", "for", "from", "if",
+        "import", "in", "is", "lambda", "None", "not", "or", "pass", "raise", "return", "True", "try", "while", "with", "yield",
+    ];
+    const JS: &[&str] = &[
+        "async", "await", "break", "case", "class", "const", "else", "export", "false", "for", "function", "if",
+        "import", "interface", "let", "new", "null", "return", "switch", "this", "throw", "true", "try", "type", "var", "while",
+    ];
+    const SHELL: &[&str] = &["case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if", "in", "then", "while"];
+    const GO: &[&str] = &[
+        "break", "case", "chan", "const", "defer", "else", "false", "for", "func", "go", "if", "import", "interface",
+        "map", "nil", "package", "range", "return", "struct", "switch", "true", "type", "var",
+    ];
+    const C: &[&str] = &[
+        "break", "case", "char", "class", "const", "else", "enum", "false", "for", "if", "int", "namespace", "new",
+        "public", "private", "return", "static", "struct", "switch", "true", "void", "while",
+    ];
+    match lang.to_lowercase().as_str() {
+        "rust" | "rs" => Some(("//", RUST)),
+        "python" | "py" => Some(("#", PYTHON)),
+        "javascript" | "js" | "typescript" | "ts" | "jsx" | "tsx" => Some(("//", JS)),
+        "sh" | "bash" | "shell" | "zsh" | "console" => Some(("#", SHELL)),
+        "go" => Some(("//", GO)),
+        "c" | "cpp" | "c++" | "h" | "java" | "csharp" | "cs" => Some(("//", C)),
+        "toml" | "yaml" | "yml" | "ini" => Some(("#", &[])),
+        _ => None,
+    }
+}
+
+/// Color keywords, strings, numbers and line comments of one code line
+fn highlight(line: &str, lang: &str, theme: &Theme) -> Vec<Run> {
+    let base = Style::default().bg(theme.code_bg);
+    let run = |text: &str, style: Style| Run { text: text.to_string(), style, url: None };
+    let (comment, keywords) = match syntax(lang) {
+        Some(syntax) => syntax,
+        None => return vec![run(line, base)],
+    };
+    // Rust uses ' for lifetimes, not strings
+    let quotes: &[char] = if comment == "//" && keywords.contains(&"fn") { &['"'] } else { &['"', '\''] };
+
+    let mut runs = Vec::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with(comment) {
+            runs.push(run(rest, base.fg(theme.comment)));
+            break;
+        } else if quotes.contains(&c) {
+            let mut escaped = false;
+            let end = rest[1..]
+                .char_indices()
+                .find(|&(_, ch)| {
+                    let close = ch == c && !escaped;
+                    escaped = ch == '\\' && !escaped;
+                    close
+                })
+                .map_or(rest.len(), |(i, _)| i + 2);
+            runs.push(run(&rest[..end], base.fg(theme.string)));
+            end
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).unwrap_or(rest.len());
+            let word = &rest[..end];
+            let style = if keywords.contains(&word) {
+                base.fg(theme.keyword).add_modifier(Modifier::BOLD)
+            } else if c.is_ascii_digit() {
+                base.fg(theme.number)
+            } else {
+                base
+            };
+            runs.push(run(word, style));
+            end
+        } else {
+            runs.push(run(&rest[..c.len_utf8()], base));
+            c.len_utf8()
+        };
+        rest = &rest[len..];
+    }
+    runs
+}
+
+/// Word wrap tagged characters to `width` display columns. Wide characters
+/// count double; a word longer than a row is broken where it hits the edge.
+/// Spaces at a break are dropped.
+pub fn wrap<T: Copy>(cells: &[(char, T)], width: usize) -> Vec<Vec<(char, T)>> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    let mut row: Vec<(char, T)> = Vec::new();
+    let mut row_width = 0;
+    let trimmed = |mut row: Vec<(char, T)>| {
+        while row.last().is_some_and(|(c, _)| *c == ' ') {
+            row.pop();
+        }
+        row
+    };
+
+    for word in cells.split_inclusive(|(c, _)| *c == ' ') {
+        let word_width: usize = word.iter().filter(|(c, _)| *c != ' ').map(|(c, _)| c.width().unwrap_or(0)).sum();
+        if row_width + word_width > width && !row.is_empty() {
+            rows.push(trimmed(std::mem::take(&mut row)));
+            row_width = 0;
+        }
+        for &(c, tag) in word {
+            let w = c.width().unwrap_or(0);
+            if row_width + w > width {
+                if c == ' ' {
+                    continue;
+                }
+                rows.push(trimmed(std::mem::take(&mut row)));
+                row_width = 0;
+            }
+            row.push((c, tag));
+            row_width += w;
+        }
+    }
+    rows.push(trimmed(row));
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(rendered: &Rendered) -> Vec<String> {
+        rendered
+            .lines
+            .iter()
+            .map(|l| l.0.iter().map(|s| s.content.as_ref()).collect::<String>())
+            .collect()
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let md = "# Title\n\nSome **bold** and `code`, see [docs](https://docs.rs).\n\n\
+                  - one\n- two\n  1. nested\n\n\
+                  ```rust\nfn main() {}\n```\n\n\
+                  | Name | Size |\n|:-----|-----:|\n| a | 1 |\n| long name | 22 |\n";
+        let theme = Theme::default();
+        let rendered = render(md, 40, &theme);
+        let lines = text(&rendered);
+
+        assert_eq!(lines[0], "Title");
+        assert_eq!(lines[1], "");
+        assert_eq!(lines[2], "Some bold and code, see docs.");
+        assert_eq!(lines[4], "\u{2022} one");
+        assert_eq!(lines[5], "\u{2022} two");
+        assert_eq!(lines[6], "  1. nested");
+        assert_eq!(lines[8], "[1] rust");
+        assert_eq!(lines[9].trim_end(), " fn main() {}");
+        assert_eq!(lines[9].width(), 40);
+        assert_eq!(lines[11], "Name      \u{2502} Size");
+        assert_eq!(lines[13], "a         \u{2502}    1");
+        assert_eq!(
+            rendered.links,
+            [LinkSpan { row: 2, col: 24, text: "docs".to_string(), url: "https://docs.rs".to_string() }]
+        );
+
+        let bold = &rendered.lines[2].0[2];
+        assert_eq!(bold.content, "bold");
+        assert!(bold.style.add_modifier.contains(Modifier::BOLD));
+        let keyword = &rendered.lines[9].0[2];
+        assert_eq!(keyword.content, "fn");
+        assert_eq!(keyword.style.fg, Some(theme.keyword));
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        let md = "Try:\n\n```rust title=x\nfn main() {}\n```\n\n    indented\n\n```\nplain\n```\n";
+        let blocks = code_blocks(md);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0], CodeBlock { lang: "rust".to_string(), code: "fn main() {}\n".to_string() });
+        assert_eq!(blocks[1].code, "indented\n");
+        assert_eq!(blocks[2].lang, "");
+    }
+
+    #[test]
+    fn test_wrap_keeps_links_and_indent() {
+        let md = "1. a list item that is long enough to wrap [here](https://example.com)";
+        let rendered = render(md, 24, &Theme::default());
+        assert_eq!(
+            text(&rendered),
+            ["1. a list item that is", "   long enough to wrap", "   here"]
+        );
+        assert_eq!(rendered.links[0].row, 2);
+        assert_eq!(rendered.links[0].col, 3);
+    }
+}
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+
+use crate::qvault_cmd::{CmdOutput, CommandRegistry, QvaultCmd};
+use crate::qvault_config::QvaultConfig;
+use crate::qvault_log;
+
+/// Scripts may /source other scripts, up to this depth
+const MAX_SOURCE_DEPTH: usize = 4;
+
+thread_local! {
+    static SOURCE_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Json,
+}
+
+impl ReportFormat {
+    /// JSON for `.json` report files, markdown for anything else
+    pub fn from_path(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".json") {
+            ReportFormat::Json
+        } else {
+            ReportFormat::Markdown
+        }
+    }
+}
+
+/// One script line and what it produced
+#[derive(Debug)]
+pub struct ReportEntry {
+    pub line: usize,
+    pub command: String,
+    pub result: Result<CmdOutput, String>,
+}
+
+/// Everything a script run produced, in script order
+#[derive(Debug)]
+pub struct Report {
+    pub script: String,
+    pub started: DateTime<Utc>,
+    pub entries: Vec<ReportEntry>,
+}
+
+impl Report {
+    pub fn failures(&self) -> usize {
+        self.entries.iter().filter(|e| e.result.is_err()).count()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Ran {} commands from {}, {} failed",
+            self.entries.len(),
+            self.script,
+            self.failures()
+        )
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Json => self.to_json().to_string(),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# qvault report: {}\n\n", self.script);
+        md.push_str(&format!("{}. Started {}.\n", self.summary(), self.started.format("%Y-%m-%d %H:%M UTC")));
+
+        for entry in &self.entries {
+            md.push_str(&format!("\n## `{}`\n\n", entry.command));
+            match &entry.result {
+                Err(err) => md.push_str(&format!("> **Error** (line {}): {}\n", entry.line, err)),
+                Ok(CmdOutput::Results { result, .. }) => {
+                    for item in result.items() {
+                        if item.description().is_empty() {
+                            md.push_str(&format!("- [{}]({})\n", item.title(), item.url()));
+                        } else {
+                            md.push_str(&format!("- [{}]({}) - {}\n", item.title(), item.url(), item.description()));
+                        }
+                    }
+                }
+                Ok(CmdOutput::Link { title, url }) => md.push_str(&format!("[{}]({})\n", title, url)),
+                Ok(CmdOutput::Lines { lines, .. }) => {
+                    for line in lines {
+                        md.push_str(&format!("- {}\n", line));
+                    }
+                }
+                Ok(output) => {
+                    let text = output.to_context().unwrap_or_default();
+                    md.push_str(if text.is_empty() { "_No output_" } else { &text });
+                    md.push('\n');
+                }
+            }
+        }
+        md
+    }
+
+    pub fn to_json(&self) -> Value {
+        let entries: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut value = json!({ "line": entry.line, "command": entry.command, "ok": entry.result.is_ok() });
+                match &entry.result {
+                    Err(err) => value["error"] = json!(err),
+                    Ok(CmdOutput::Results { query, result }) => {
+                        value["query"] = json!(query);
+                        value["results"] = json!(result.items());
+                    }
+                    Ok(CmdOutput::Link { url, .. }) => value["url"] = json!(url),
+                    Ok(output) => value["output"] = json!(output.to_context()),
+                }
+                value
+            })
+            .collect();
+        json!({
+            "script": self.script,
+            "started": self.started.to_rfc3339(),
+            "failed": self.failures(),
+            "entries": entries,
+        })
+    }
+
+    /// Write the report, the format follows the file extension
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.render(ReportFormat::from_path(path)))
+            .map_err(|e| format!("Cannot write report {}: {}", path, e))
+    }
+}
+
+/// Default report location next to the script, `monday.qv` -> `monday.report.md`
+pub fn default_report_path(script: &str) -> String {
+    let path = Path::new(script);
+    path.with_extension("report.md").to_string_lossy().into_owned()
+}
+
+/// Run every command in a script file without the full-screen UI.
+/// One command or pipeline per line; blank lines and lines starting with
+/// `#` are skipped. A failing line is recorded and the script goes on,
+/// `/exit` stops it.
+pub fn run_script(path: &str, registry: &CommandRegistry, config: &mut QvaultConfig) -> Result<Report, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+
+    let depth = SOURCE_DEPTH.with(|d| d.get());
+    if depth >= MAX_SOURCE_DEPTH {
+        return Err(format!("Scripts nested more than {} deep, check {} for a loop", MAX_SOURCE_DEPTH, path));
+    }
+    SOURCE_DEPTH.with(|d| d.set(depth + 1));
+    let report = run_lines(path, &text, registry, config);
+    SOURCE_DEPTH.with(|d| d.set(depth));
+    Ok(report)
+}
+
+fn run_lines(path: &str, text: &str, registry: &CommandRegistry, config: &mut QvaultConfig) -> Report {
+    let mut report = Report { script: path.to_string(), started: Utc::now(), entries: Vec::new() };
+    qvault_log::log_info("Running script: ", format_args!("{}", path));
+
+    for (idx, line) in text.lines().enumerate() {
+        let command = line.trim();
+        if command.is_empty() || command.starts_with('#') {
+            continue;
+        }
+
+        let result = QvaultCmd::parse_pipeline(command, registry, config)
+            .and_then(|stages| registry.run_pipeline(&stages, None, config).map_err(|e| e.to_string()));
+        if let Err(err) = &result {
+            qvault_log::log_warn(format!("{}:{}: {}", path, idx + 1, err));
+        }
+        let exit = matches!(result, Ok(CmdOutput::Exit));
+        report.entries.push(ReportEntry { line: idx + 1, command: command.to_string(), result });
+        if exit {
+            break;
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_script_report() {
+        let path = std::env::temp_dir().join(format!("qvault_script_{}.qv", std::process::id()));
+        fs::write(&path, "# weekly\n\n/alias\n/nosuch thing\n/help | /search x\n/exit\n/help\n").unwrap();
+        let registry = CommandRegistry::with_builtins();
+        let mut config = QvaultConfig::default();
+
+        let report = run_script(path.to_str().unwrap(), &registry, &mut config);
+        fs::remove_file(&path).unwrap();
+        let report = report.unwrap();
+
+        let lines: Vec<usize> = report.entries.iter().map(|e| e.line).collect();
+        assert_eq!(lines, [3, 4, 5, 6]);
+        assert_eq!(report.failures(), 2);
+
+        let md = report.to_markdown();
+        assert!(md.contains("## `/nosuch thing`"));
+        assert!(md.contains("/search does not take piped input"));
+        let json = report.to_json();
+        assert_eq!(json["entries"][0]["ok"], true);
+        assert_eq!(json["entries"][1]["line"], 4);
+        assert_eq!(default_report_path("weekly.qv"), "weekly.report.md");
+    }
+}
@@ -0,0 +1,31 @@
+use std::future::Future;
+
+use once_cell::sync::Lazy;
+use tokio::runtime::{Builder, Runtime};
+use tokio::task::JoinHandle;
+
+/// Runtime for network requests. The UI stays on the main thread and
+/// polls the handles, so two workers are plenty.
+static RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("qvault-net")
+        .enable_all()
+        .build()
+        .expect("cannot start the tokio runtime")
+});
+
+/// Start a request in the background
+pub fn spawn<F>(task: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    RUNTIME.spawn(task)
+}
+
+/// Run a request to the end on the calling thread, for scripts and the
+/// one-shot subcommands where nothing else needs to happen meanwhile
+pub fn block_on<F: Future>(task: F) -> F::Output {
+    RUNTIME.block_on(task)
+}
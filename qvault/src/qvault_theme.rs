@@ -0,0 +1,400 @@
+use std::collections::BTreeMap;
+use tui::style::Color;
+
+use crate::qvault_config::QvaultConfig;
+
+/// Themes that ship with qvault. `[themes.NAME]` in qvault.toml adds more.
+pub const BUILTIN_THEMES: &[&str] = &["dark", "light", "high-contrast"];
+
+/// Symbols drawn around the text. The ASCII set is for serial consoles,
+/// terminals without emoji fonts and screen readers.
+#[derive(Debug, PartialEq)]
+pub struct Glyphs {
+    /// Box borders are redrawn in ASCII too
+    pub ascii: bool,
+    pub prompt: &'static str,
+    /// In front of status bar notes
+    pub bar: &'static str,
+    /// Right end of the status bar
+    pub bar_end: &'static str,
+    /// In front of status bar errors
+    pub error: &'static str,
+    /// Both sides of an output title
+    pub title: &'static str,
+    /// First line of an output message, four columns wide
+    pub bullet: &'static str,
+    pub url: &'static str,
+    /// Markdown list bullets by nesting depth
+    pub list: [&'static str; 3],
+    pub quote: &'static str,
+    /// Horizontal rules and the table header line
+    pub rule: &'static str,
+    pub column: &'static str,
+    pub cross: &'static str,
+    /// End of a cut table cell
+    pub ellipsis: &'static str,
+    /// Frames of the busy spinner in the status bar
+    pub spinner: &'static [&'static str],
+}
+
+pub static UNICODE_GLYPHS: Glyphs = Glyphs {
+    ascii: false,
+    prompt: "\u{1F50D}>",
+    bar: "\u{1F30D}  \u{1F4DA}  ",
+    bar_end: "\u{1F5C4} ",
+    error: " \u{26A0}  ",
+    title: "\u{1F340}\u{1F340}\u{1F340}\u{1F340}\u{1F340}",
+    bullet: "\u{1F7E2}  ",
+    url: "\u{1F7E2} URL : ",
+    list: ["\u{2022} ", "\u{25E6} ", "\u{25AA} "],
+    quote: "\u{2502} ",
+    rule: "\u{2500}",
+    column: " \u{2502} ",
+    cross: "\u{2500}\u{253C}\u{2500}",
+    ellipsis: "\u{2026}",
+    spinner: &[
+        "\u{280B}", "\u{2819}", "\u{2839}", "\u{2838}", "\u{283C}", "\u{2834}", "\u{2826}", "\u{2827}", "\u{2807}", "\u{280F}",
+    ],
+};
+
+pub static ASCII_GLYPHS: Glyphs = Glyphs {
+    ascii: true,
+    prompt: ">",
+    bar: " ",
+    bar_end: "",
+    error: " ! ",
+    title: "==",
+    bullet: "*   ",
+    url: "URL: ",
+    list: ["* ", "- ", "+ "],
+    quote: "| ",
+    rule: "-",
+    column: " | ",
+    cross: "-+-",
+    ellipsis: "~",
+    spinner: &["|", "/", "-", "\\"],
+};
+
+/// Colors and glyphs of every part of the screen
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// False under NO_COLOR: every color is the terminal default and
+    /// selections use reverse video
+    pub color: bool,
+    pub glyphs: &'static Glyphs,
+    /// Status bar
+    pub bar_bg: Color,
+    pub bar_fg: Color,
+    /// Errors in the bar, error lines in the log
+    pub error: Color,
+    pub warn: Color,
+    /// Headings and field labels
+    pub title: Color,
+    /// Links and result URLs
+    pub url: Color,
+    /// Selected search result
+    pub highlight_bg: Color,
+    pub highlight_fg: Color,
+    /// Hints, numbering and descriptions
+    pub dim: Color,
+    /// Result domains, the Submit button
+    pub accent: Color,
+    /// Inline code and fenced code blocks
+    pub code_bg: Color,
+    pub code_fg: Color,
+    /// Syntax colors inside code blocks
+    pub keyword: Color,
+    pub string: Color,
+    pub number: Color,
+    pub comment: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    fn dark() -> Self {
+        Theme {
+            name: "dark".to_string(),
+            color: true,
+            glyphs: &UNICODE_GLYPHS,
+            bar_bg: Color::Blue,
+            bar_fg: Color::White,
+            error: Color::Red,
+            warn: Color::Yellow,
+            title: Color::Cyan,
+            url: Color::Cyan,
+            highlight_bg: Color::Blue,
+            highlight_fg: Color::White,
+            dim: Color::DarkGray,
+            accent: Color::Green,
+            code_bg: Color::Indexed(236),
+            code_fg: Color::Yellow,
+            keyword: Color::Magenta,
+            string: Color::Green,
+            number: Color::Cyan,
+            comment: Color::DarkGray,
+        }
+    }
+
+    fn light() -> Self {
+        Theme {
+            name: "light".to_string(),
+            color: true,
+            glyphs: &UNICODE_GLYPHS,
+            bar_bg: Color::Indexed(252),
+            bar_fg: Color::Black,
+            error: Color::Indexed(160),
+            warn: Color::Indexed(130),
+            title: Color::Indexed(25),
+            url: Color::Indexed(25),
+            highlight_bg: Color::Indexed(153),
+            highlight_fg: Color::Black,
+            dim: Color::Indexed(244),
+            accent: Color::Indexed(28),
+            code_bg: Color::Indexed(254),
+            code_fg: Color::Indexed(88),
+            keyword: Color::Indexed(90),
+            string: Color::Indexed(28),
+            number: Color::Indexed(25),
+            comment: Color::Indexed(244),
+        }
+    }
+
+    /// Bright colors only, nothing gray
+    fn high_contrast() -> Self {
+        Theme {
+            name: "high-contrast".to_string(),
+            color: true,
+            glyphs: &UNICODE_GLYPHS,
+            bar_bg: Color::White,
+            bar_fg: Color::Black,
+            error: Color::Red,
+            warn: Color::LightYellow,
+            title: Color::LightYellow,
+            url: Color::LightCyan,
+            highlight_bg: Color::White,
+            highlight_fg: Color::Black,
+            dim: Color::Reset,
+            accent: Color::LightGreen,
+            code_bg: Color::Black,
+            code_fg: Color::White,
+            keyword: Color::LightMagenta,
+            string: Color::LightGreen,
+            number: Color::LightCyan,
+            comment: Color::White,
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// Built-in theme, or a user theme from config, adjusted to the
+    /// `[display]` settings, NO_COLOR and TERM. A user theme starts from
+    /// `base` (dark unless set) and overrides single colors.
+    pub fn load(name: &str, config: &QvaultConfig) -> Result<Theme, String> {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let term = std::env::var("TERM").unwrap_or_default();
+        Theme::load_for(name, config, no_color, &term)
+    }
+
+    /// Like load, with NO_COLOR and TERM given rather than read
+    pub fn load_for(name: &str, config: &QvaultConfig, no_color: bool, term: &str) -> Result<Theme, String> {
+        let name = name.to_lowercase();
+        let mut theme = match config.themes.get(&name) {
+            Some(colors) => Theme::user(&name, colors)?,
+            None => Theme::builtin(&name)
+                .ok_or_else(|| format!("No theme named {}, try one of {}", name, theme_names(config).join(", ")))?,
+        };
+        if !color_enabled(config, no_color) {
+            theme.color = false;
+            for key in COLOR_KEYS {
+                if let Some(slot) = theme.slot(key) {
+                    *slot = Color::Reset;
+                }
+            }
+        }
+        if ascii_enabled(config, term) {
+            theme.glyphs = &ASCII_GLYPHS;
+        }
+        Ok(theme)
+    }
+
+    fn user(name: &str, colors: &BTreeMap<String, String>) -> Result<Theme, String> {
+        let base = colors.get("base").map(String::as_str).unwrap_or("dark");
+        let mut theme = Theme::builtin(base).ok_or_else(|| format!("Theme {}: base must be one of {}", name, BUILTIN_THEMES.join(", ")))?;
+        theme.name = name.to_string();
+        for (key, value) in colors.iter().filter(|(k, _)| *k != "base") {
+            let color = parse_color(value).map_err(|e| format!("Theme {}: {}", name, e))?;
+            *theme.slot(key).ok_or_else(|| format!("Theme {}: unknown color {}", name, key))? = color;
+        }
+        Ok(theme)
+    }
+
+    /// The theme named in config, dark when none is set
+    pub fn from_config(config: &QvaultConfig) -> Result<Theme, String> {
+        Theme::load(config.theme.as_deref().unwrap_or("dark"), config)
+    }
+
+    fn slot(&mut self, key: &str) -> Option<&mut Color> {
+        Some(match key {
+            "bar_bg" => &mut self.bar_bg,
+            "bar_fg" => &mut self.bar_fg,
+            "error" => &mut self.error,
+            "warn" => &mut self.warn,
+            "title" => &mut self.title,
+            "url" => &mut self.url,
+            "highlight_bg" => &mut self.highlight_bg,
+            "highlight_fg" => &mut self.highlight_fg,
+            "dim" => &mut self.dim,
+            "accent" => &mut self.accent,
+            "code_bg" => &mut self.code_bg,
+            "code_fg" => &mut self.code_fg,
+            "keyword" => &mut self.keyword,
+            "string" => &mut self.string,
+            "number" => &mut self.number,
+            "comment" => &mut self.comment,
+            _ => return None,
+        })
+    }
+}
+
+/// Color names accepted in `[themes.NAME]`
+const COLOR_KEYS: &[&str] = &[
+    "bar_bg", "bar_fg", "error", "warn", "title", "url", "highlight_bg", "highlight_fg", "dim", "accent", "code_bg",
+    "code_fg", "keyword", "string", "number", "comment",
+];
+
+/// Colors are on unless `[display] color = false` or NO_COLOR is set
+/// (https://no-color.org)
+pub fn color_enabled(config: &QvaultConfig, no_color: bool) -> bool {
+    config.display.color && !no_color
+}
+
+/// ASCII glyphs with `[display] ascii = true`, and on the Linux console,
+/// serial terminals and dumb terminals that have no emoji, going by TERM
+pub fn ascii_enabled(config: &QvaultConfig, term: &str) -> bool {
+    config.display.ascii || term == "linux" || term == "dumb" || term.starts_with("vt")
+}
+
+/// Built-in themes followed by the user's
+pub fn theme_names(config: &QvaultConfig) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_THEMES.iter().map(|n| n.to_string()).collect();
+    names.extend(config.themes.keys().filter(|n| !BUILTIN_THEMES.contains(&n.as_str())).cloned());
+    names
+}
+
+/// `blue`, `lightred`, `#1e1e2e` or a 256 color palette index like `236`
+pub fn parse_color(text: &str) -> Result<Color, String> {
+    let text = text.trim().to_lowercase();
+    if let Some(hex) = text.strip_prefix('#') {
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok());
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(format!("bad color {}, expected #rrggbb", text)),
+        };
+    }
+    if let Ok(index) = text.parse::<u8>() {
+        return Ok(Color::Indexed(index));
+    }
+    let named: BTreeMap<&str, Color> = [
+        ("default", Color::Reset),
+        ("reset", Color::Reset),
+        ("black", Color::Black),
+        ("red", Color::Red),
+        ("green", Color::Green),
+        ("yellow", Color::Yellow),
+        ("blue", Color::Blue),
+        ("magenta", Color::Magenta),
+        ("cyan", Color::Cyan),
+        ("gray", Color::Gray),
+        ("darkgray", Color::DarkGray),
+        ("lightred", Color::LightRed),
+        ("lightgreen", Color::LightGreen),
+        ("lightyellow", Color::LightYellow),
+        ("lightblue", Color::LightBlue),
+        ("lightmagenta", Color::LightMagenta),
+        ("lightcyan", Color::LightCyan),
+        ("white", Color::White),
+    ]
+    .into_iter()
+    .collect();
+    named.get(text.replace(['_', '-'], "").as_str()).copied().ok_or_else(|| format!("unknown color {}", text))
+}
+
+/// SGR sequence setting `color` as the foreground, for text written
+/// outside of tui such as the OSC 8 link overlay
+pub fn ansi_fg(color: Color) -> String {
+    let code = match color {
+        Color::Reset => "39".to_string(),
+        Color::Black => "30".to_string(),
+        Color::Red => "31".to_string(),
+        Color::Green => "32".to_string(),
+        Color::Yellow => "33".to_string(),
+        Color::Blue => "34".to_string(),
+        Color::Magenta => "35".to_string(),
+        Color::Cyan => "36".to_string(),
+        Color::Gray => "37".to_string(),
+        Color::DarkGray => "90".to_string(),
+        Color::LightRed => "91".to_string(),
+        Color::LightGreen => "92".to_string(),
+        Color::LightYellow => "93".to_string(),
+        Color::LightBlue => "94".to_string(),
+        Color::LightMagenta => "95".to_string(),
+        Color::LightCyan => "96".to_string(),
+        Color::White => "97".to_string(),
+        Color::Indexed(i) => format!("38;5;{}", i),
+        Color::Rgb(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+    };
+    format!("\x1b[{}m", code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_themes() {
+        let load = |name: &str, config: &QvaultConfig| Theme::load_for(name, config, false, "xterm-256color");
+        let mut config = QvaultConfig::default();
+        assert_eq!(Theme::from_config(&config).unwrap().name, "dark");
+        assert_eq!(load("Light", &config).unwrap().bar_fg, Color::Black);
+        assert!(load("nope", &config).unwrap_err().contains("high-contrast"));
+
+        let mut colors = BTreeMap::new();
+        colors.insert("base".to_string(), "light".to_string());
+        colors.insert("bar_bg".to_string(), "#1e1e2e".to_string());
+        colors.insert("url".to_string(), "33".to_string());
+        config.themes.insert("mocha".to_string(), colors);
+        let theme = load("mocha", &config).unwrap();
+        assert_eq!(theme.bar_bg, Color::Rgb(0x1e, 0x1e, 0x2e));
+        assert_eq!(theme.url, Color::Indexed(33));
+        assert_eq!(theme.bar_fg, Color::Black);
+        assert_eq!(theme_names(&config), ["dark", "light", "high-contrast", "mocha"]);
+
+        config.themes.get_mut("mocha").unwrap().insert("bar".to_string(), "red".to_string());
+        assert_eq!(load("mocha", &config).unwrap_err(), "Theme mocha: unknown color bar");
+        let plain = Theme::load_for("light", &config, true, "linux").unwrap();
+        assert!(!plain.color);
+        assert_eq!((plain.bar_bg, plain.url), (Color::Reset, Color::Reset));
+        assert_eq!(plain.glyphs, &ASCII_GLYPHS);
+        assert!(color_enabled(&config, false) && !ascii_enabled(&config, "xterm"));
+        assert!(ascii_enabled(&config, "vt100") && ascii_enabled(&config, "dumb"));
+        config.display.color = false;
+        config.display.ascii = true;
+        assert!(!color_enabled(&config, false) && ascii_enabled(&config, "xterm"));
+
+        assert_eq!(parse_color("Light_Red"), Ok(Color::LightRed));
+        assert!(parse_color("#12345").is_err());
+    }
+}
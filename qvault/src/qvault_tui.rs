@@ -1,470 +1,629 @@
-use std::io::{self, Write};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Stdout, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+use log::Level;
+use termion::input::MouseTerminal;
 use termion::raw::{IntoRawMode, RawTerminal};
-use termion::{clear, cursor, color};
-use termion::{
-    event::{Event, Key},
-    input::TermRead,
-};
+use termion::{cursor, style};
+use termion::event::{self, Event, Key, MouseButton, MouseEvent};
+
+use base64::prelude::*;
+use tui::backend::{Backend, TermionBackend};
+use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans, Text};
+use tui::buffer::Buffer;
+use tui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Widget, Wrap};
+use tui::{Frame, Terminal};
+use tokio::task::JoinHandle;
+
+use crate::qvault_cmd::{self, qvault_search::SearchResult};
+use crate::qvault_keymap::{Action, Keymap};
+use crate::qvault_log;
+use crate::qvault_markdown::{self, LinkSpan, Rendered};
+use crate::qvault_task;
+use crate::qvault_theme::{self, Theme};
+
+/// Write end of the pipe the SIGWINCH handler pokes, -1 until installed
+static RESIZE_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn on_resize(_: libc::c_int) {
+    let fd = RESIZE_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // SAFETY: write(2) is async-signal-safe; a full pipe already means "resized"
+        unsafe { libc::write(fd, b"w".as_ptr().cast(), 1) };
+    }
+}
+
+/// Pipe that becomes readable when the window is resized, so a resize
+/// wakes up the same poll that waits for keys
+fn resize_pipe() -> io::Result<File> {
+    let mut fds = [0 as libc::c_int; 2];
+    // SAFETY: fds has room for the two descriptors pipe2 fills in
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    RESIZE_PIPE.store(fds[1], Ordering::Relaxed);
+    // SAFETY: on_resize only touches the atomic and calls write(2)
+    unsafe { libc::signal(libc::SIGWINCH, on_resize as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+    // SAFETY: fds[0] is a fresh descriptor nothing else owns
+    Ok(unsafe { File::from_raw_fd(fds[0]) })
+}
+
+/// How often the spinner moves while a request runs
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+
+/// Screen column and row, URL and visible text of a link for the OSC 8 overlay
+type LinkSpot = (u16, u16, String, String);
+
+/// Text shown in the status bar
+#[derive(Debug, Clone)]
+enum Status {
+    Info(String),
+    Error(String),
+}
+
+/// Title, messages, link and help box of ordinary command output
+#[derive(Debug, Clone, Default)]
+struct TextPane {
+    title: Option<String>,
+    /// (line, text), drawn in line order
+    messages: Vec<(u16, String)>,
+    url: Option<String>,
+    help: Option<Vec<String>>,
+    /// First wrapped message or help row in view
+    scroll: usize,
+    /// Messages are markdown, e.g. an AI answer
+    markdown: bool,
+}
+
+/// What the output pane shows
+#[derive(Debug, Clone)]
+enum Pane {
+    Text(TextPane),
+    Results { query: String, result: SearchResult },
+    Settings { brave: String, openai: String, active: usize, note: Option<String> },
+    /// Lines already filtered; the visible window is picked at draw time
+    Log { title: String, lines: Vec<String>, scroll: usize, footer: String },
+}
+
+impl Pane {
+    /// Name in the tab strip, `None` for a tab with nothing in it
+    fn label(&self) -> Option<String> {
+        match self {
+            Pane::Text(pane) => pane.title.clone().or_else(|| {
+                let used = !pane.messages.is_empty() || pane.help.is_some() || pane.url.is_some();
+                used.then(|| "output".to_string())
+            }),
+            Pane::Results { query, .. } => Some(query.clone()),
+            Pane::Settings { .. } => Some("settings".to_string()),
+            Pane::Log { .. } => Some("log".to_string()),
+        }
+    }
+}
+
+/// Number of tabs, switched with Alt-1..Alt-9 or /tab N
+pub const TAB_COUNT: usize = 9;
+
+/// Longest tab label in the strip, in columns
+const TAB_LABEL_WIDTH: usize = 12;
+
+/// Output of a tab in the background. The active tab lives in `Screen`.
+struct Tab {
+    pane: Pane,
+    results_offset: usize,
+}
+
+impl Default for Tab {
+    fn default() -> Self {
+        Tab { pane: Pane::Text(TextPane::default()), results_offset: 0 }
+    }
+}
+
+/// Everything on screen. Each change redraws the whole frame from this
+/// state; tui only sends the cells that changed.
+struct Screen {
+    /// Output of the active tab
+    pane: Pane,
+    status: Status,
+    prompt: String,
+    /// Cursor sits in the input line while the user is typing
+    typing: bool,
+    /// Byte offset of the caret in `prompt`
+    caret: usize,
+    /// First result shown in the list, kept here so a click maps to an item
+    results_offset: usize,
+    /// Every tab; the slot of the active one is empty while it is shown
+    tabs: Vec<Tab>,
+    active: usize,
+    theme: Theme,
+    keymap: Keymap,
+}
+
+/// Header, output, status bar and input line, top to bottom
+fn screen_layout(area: Rect) -> Vec<Rect> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1), Constraint::Length(1)])
+        .split(area)
+}
 
 pub struct QvaultTerminal {
-    terminal: RawTerminal<io::Stdout>,
-    input_row: u16,
-    input_col: u16,
-    last_input_row: u16,
-    last_input_col: u16,
-    output_row: u16,
-    hbar_row: u16,
+    tui: Terminal<TermionBackend<MouseTerminal<RawTerminal<Stdout>>>>,
+    input: File, // Keys come from the controlling tty, unbuffered so it can be polled
+    pending: VecDeque<u8>, // Bytes read from the tty but not yet parsed into events
+    resized: File, // Readable after SIGWINCH
+    screen: Screen,
+    links: Vec<LinkSpot>, // Where the links of the last frame went, for clicks
+    opener: Option<String>, // `[pager] opener` for clicked links
 }
 
 impl QvaultTerminal {
-    // Constructor to initialize the terminal in raw mode with mouse support
+    // Constructor to initialize the terminal in raw mode with mouse reporting
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let terminal = io::stdout().into_raw_mode()?; // Convert stdout into raw mode
+        let stdout = MouseTerminal::from(io::stdout().into_raw_mode()?);
+        let input = termion::get_tty()?;
 
         Ok(QvaultTerminal {
-            terminal,
-            input_row: 1,
-            input_col: 1,
-            last_input_col: 0,
-            last_input_row: 0,
-            output_row: 1,
-            hbar_row: 1,
+            tui: Terminal::new(TermionBackend::new(stdout))?,
+            input,
+            pending: VecDeque::new(),
+            resized: resize_pipe()?,
+            links: Vec::new(),
+            opener: None,
+            screen: Screen {
+                pane: Pane::Text(TextPane::default()),
+                status: Status::Info(String::new()),
+                prompt: String::new(),
+                typing: false,
+                caret: 0,
+                results_offset: 0,
+                tabs: (0..TAB_COUNT).map(|_| Tab::default()).collect(),
+                active: 0,
+                theme: Theme::default(),
+                keymap: Keymap::default(),
+            },
         })
     }
 
-    pub fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        //self.terminal.show_cursor()?; // Ensure cursor is shown when shutting down
-        let _ = self.terminal.flush();
+    /// Wait up to `timeout` for input on the tty, `None` waits forever.
+    /// A window resize in the meantime re-renders the screen at the new
+    /// size and keeps waiting.
+    fn poll_input(&mut self, timeout: Option<Duration>) -> bool {
+        loop {
+            let mut fds = [
+                libc::pollfd { fd: self.input.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+                libc::pollfd { fd: self.resized.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+            ];
+            let millis = timeout.map_or(-1, |t| t.as_millis() as libc::c_int);
+            // SAFETY: two valid pollfds on descriptors we own for the lifetime of self
+            let ready = unsafe { libc::poll(fds.as_mut_ptr(), 2, millis) };
+            if ready > 0 && (fds[0].revents & libc::POLLIN) != 0 {
+                return true;
+            }
+            if ready > 0 && (fds[1].revents & libc::POLLIN) != 0 {
+                let mut buf = [0u8; 64];
+                while matches!(self.resized.read(&mut buf), Ok(n) if n > 0) {}
+                qvault_log::log_info("Terminal resized to ", format_args!("{:?}", termion::terminal_size()));
+                let _ = self.draw();
+                if timeout.is_some() {
+                    return false;
+                }
+                continue;
+            }
+            // Interrupted by a signal before the pipe was written: wait again
+            if ready < 0 && timeout.is_none() {
+                continue;
+            }
+            return false;
+        }
+    }
 
-        Ok(())
+    /// Next key or mouse event. Returns `None` if nothing arrived within `timeout`.
+    /// Everything available is read in one go and parsed from `pending`, so bursts
+    /// (paste, escape sequences) are never split or dropped between calls.
+    pub fn read_event(&mut self, timeout: Option<Duration>) -> io::Result<Option<Event>> {
+        if self.pending.is_empty() {
+            if !self.poll_input(timeout) {
+                return Ok(None);
+            }
+            let mut buf = [0u8; 1024];
+            let n = self.input.read(&mut buf)?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "tty closed"));
+            }
+            self.pending.extend(&buf[..n]);
+        }
+
+        let first = match self.pending.pop_front() {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        // termion only parses Esc as the start of a sequence, a lone one is the key
+        if first == 0x1b && self.pending.is_empty() {
+            return Ok(Some(Event::Key(Key::Esc)));
+        }
+        let mut rest = std::iter::from_fn(|| self.pending.pop_front().map(Ok));
+        event::parse_event(first, &mut rest).map(Some)
     }
 
-    pub fn show_msg(&mut self, msg: String) -> Result<(), Box<dyn std::error::Error>> {
-        writeln!(self.terminal, "{}{}", cursor::Goto(1, 2), msg)?;
-        self.terminal.flush()?;
+    /// Put a typed character, or Alt and a character, back in front of the
+    /// input so the prompt reads it next
+    fn push_back(&mut self, event: &Event) {
+        let (alt, c) = match event {
+            Event::Key(Key::Char(c)) => (false, *c),
+            Event::Key(Key::Alt(c)) => (true, *c),
+            _ => return,
+        };
+        let mut buf = [0u8; 4];
+        for b in c.encode_utf8(&mut buf).bytes().rev() {
+            self.pending.push_front(b);
+        }
+        if alt {
+            self.pending.push_front(0x1b);
+        }
+    }
 
+    /// Render the whole screen from `self.screen`
+    fn draw(&mut self) -> io::Result<()> {
+        let screen = &mut self.screen;
+        let mut links: Vec<LinkSpot> = Vec::new();
+        let mut caret: Option<(u16, u16)> = None;
+        self.tui.draw(|f| {
+            let (l, c) = draw_screen(f, screen);
+            links = l;
+            caret = c;
+        })?;
+
+        // tui cells cannot carry OSC 8, so the link text is written over
+        // itself wrapped in the hyperlink sequence
+        let link_color = qvault_theme::ansi_fg(self.screen.theme.url);
+        let backend = self.tui.backend_mut();
+        for (x, y, url, text) in &links {
+            write!(
+                backend,
+                "{}{}{}\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\{}",
+                cursor::Goto(x + 1, y + 1),
+                style::Underline,
+                link_color,
+                url,
+                text,
+                style::Reset
+            )?;
+        }
+        if let Some((x, y)) = caret {
+            write!(backend, "{}", cursor::Goto(x + 1, y + 1))?;
+        }
+        Backend::flush(backend)?;
+        self.links = links;
         Ok(())
     }
 
-    pub fn show_output_title(&mut self, title: String) -> Result<(), Box<dyn std::error::Error>> {
-        let title = if title.is_empty() {
-            "SEARCH RESULTS".to_string()
-        } else {
-            title
+    /// Program that opens clicked links, like `o` in the result pager
+    pub fn set_opener(&mut self, opener: Option<String>) {
+        self.opener = opener;
+    }
+
+    /// Scrolling for a mouse event: the wheel moves a row, a click on the
+    /// left or right half of the status bar pages up or down
+    fn mouse_action(&self, mouse: MouseEvent) -> Option<Action> {
+        let bar = screen_layout(self.tui.size().ok()?)[2];
+        match mouse {
+            MouseEvent::Press(MouseButton::WheelDown, _, _) => Some(Action::Next),
+            MouseEvent::Press(MouseButton::WheelUp, _, _) => Some(Action::Prev),
+            MouseEvent::Press(MouseButton::Left, x, y) if y.saturating_sub(1) == bar.y => {
+                Some(if x.saturating_sub(1) < bar.x + bar.width / 2 { Action::PageUp } else { Action::PageDown })
+            }
+            _ => None,
+        }
+    }
+
+    /// Open the link under a left click, if any. Returns the bar message.
+    fn click_link(&mut self, mouse: MouseEvent) -> Option<String> {
+        let (x, y) = match mouse {
+            MouseEvent::Press(MouseButton::Left, x, y) => (x.saturating_sub(1), y.saturating_sub(1)),
+            _ => return None,
         };
+        let (_, _, url, _) = self
+            .links
+            .iter()
+            .find(|(lx, ly, _, text)| *ly == y && (*lx..*lx + Span::raw(text.as_str()).width() as u16).contains(&x))?;
+        let url = url.clone();
+        Some(match qvault_cmd::open_url(&url, self.opener.as_deref()) {
+            Ok(()) => format!("Opened {}", url),
+            Err(err) => format!("Cannot open URL: {}", err),
+        })
+    }
 
-        // Get terminal size
-        let (term_width, _) = termion::terminal_size().unwrap();
+    /// Colors for everything drawn from now on
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.screen.theme = theme;
+        let _ = self.draw();
+    }
 
-        // Title decorations and formatting
-        let decoration = "\u{1F340}\u{1F340}\u{1F340}\u{1F340}\u{1F340}";
-        let full_title = format!("{} {} {}", decoration, title.to_uppercase(), decoration);
+    /// Key bindings of the pager and scrolling views
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.screen.keymap = keymap;
+    }
 
-        // Calculate the starting column to center the title
-        let start_col;
-        if full_title.len() < term_width.into() {
-            start_col = (term_width as usize - full_title.len()) / 2;
-        } else {
-            start_col = 1;
+    pub fn keymap(&self) -> &Keymap {
+        &self.screen.keymap
+    }
+
+    /// Show tab `index`, counted from 0. The output, scroll position and
+    /// selected result of the tab left are kept.
+    pub fn switch_tab(&mut self, index: usize) -> io::Result<()> {
+        let screen = &mut self.screen;
+        if index == screen.active || index >= screen.tabs.len() {
+            return Ok(());
         }
+        let shown = Tab {
+            pane: std::mem::replace(&mut screen.pane, Pane::Text(TextPane::default())),
+            results_offset: screen.results_offset,
+        };
+        screen.tabs[screen.active] = shown;
+        let tab = std::mem::take(&mut screen.tabs[index]);
+        screen.pane = tab.pane;
+        screen.results_offset = tab.results_offset;
+        screen.active = index;
+        self.draw()
+    }
 
-        // Print the title at the centered position
-        writeln!(
-            self.terminal,
-            "{}{}{}{}",
-            cursor::Goto(start_col as u16, self.output_row + 1), // Move cursor to centered position
-            "\x1b[1m",                                           // Start bold text
-            full_title,                                          // Full title with decorations
-            "\x1b[0m"                                            // Reset text formatting
-        ).unwrap();
+    pub fn active_tab(&self) -> usize {
+        self.screen.active
+    }
 
-        self.terminal.flush()?;
+    /// Query and results of the active tab, when it shows search results
+    pub fn tab_results(&self) -> Option<(String, SearchResult)> {
+        match &self.screen.pane {
+            Pane::Results { query, result } => Some((query.clone(), result.clone())),
+            _ => None,
+        }
+    }
 
+    /// Number and label of every tab with output in it, and of the active one
+    pub fn tab_labels(&self) -> Vec<(usize, String)> {
+        tab_strip(&self.screen)
+    }
+
+    /// Rows of the output pane
+    pub fn output_height(&self) -> u16 {
+        self.tui.size().map(|area| screen_layout(area)[1].height).unwrap_or(0)
+    }
+
+    fn text_pane(&mut self) -> &mut TextPane {
+        if !matches!(self.screen.pane, Pane::Text(_)) {
+            self.screen.pane = Pane::Text(TextPane::default());
+        }
+        match &mut self.screen.pane {
+            Pane::Text(pane) => pane,
+            _ => unreachable!("pane was just set to text"),
+        }
+    }
+
+    pub fn show_output_title(&mut self, title: String) -> Result<(), Box<dyn std::error::Error>> {
+        self.text_pane().title = Some(title);
+        self.draw()?;
         Ok(())
     }
 
     pub fn show_output_message(&mut self, line: u16, msg: String) -> Result<(), Box<dyn std::error::Error>> {
-        writeln!(
-            self.terminal,
-            "{}{}{}",
-            cursor::Goto(1, self.output_row + 5+1+line), // Move cursor to the correct position
-            "\u{1F7E2}  ", // Left border decoration
-            msg,                 // Title in uppercase
-        )?;
-        self.terminal.flush()?;
-
+        let pane = self.text_pane();
+        pane.messages.retain(|(l, _)| *l != line);
+        pane.messages.push((line, msg));
+        pane.messages.sort_by_key(|(l, _)| *l);
+        pane.scroll = 0;
+        self.draw()?;
         Ok(())
     }
 
+    /// Markdown such as an AI answer, laid out with headings, lists, code and tables
+    pub fn show_output_markdown(&mut self, body: String) -> io::Result<()> {
+        let pane = self.text_pane();
+        pane.messages = vec![(1, body)];
+        pane.markdown = true;
+        pane.scroll = 0;
+        self.draw()
+    }
+
+    /// Link at the bottom of the output, clickable through OSC 8
     pub fn show_output_url(&mut self, url: &str) {
-        // Move the cursor to the specified position
-        write!(self.terminal, "{}", cursor::Goto(1, self.output_row+15)).unwrap();
-
-        // Use the OSC 8 escape sequence to create a clickable hyperlink
-        // Format: \x1b]8;;<URL>\x1b\\<text>\x1b]8;;\x1b\\
-        let text = format!("{} URL : {}", "\u{1F7E2}", url);
-        write!(
-            self.terminal,
-            "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\",
-            url,        // The actual URL
-            text // The text to display
-        )
-        .unwrap();
-
-        // Flush the terminal to ensure the output is written
-        self.terminal.flush().unwrap();
-    }
-
-
-    pub fn show_output_nav(&mut self, num_results: usize) -> Result<(), Box<dyn std::error::Error>> {
-        // Safely get terminal dimensions
-        let (width, height) = termion::terminal_size()?;
-
-        // Center the "num Search Results"
-        let results_text = format!("{num_results} Search Results");
-        let center_x = (width / 2) as u16;
-        //let row = (height / 2) as u16;
-        let text_x = center_x.saturating_sub((results_text.len() / 2) as u16); // Safe subtraction
-
-        // Print the centered "num Search Results"
-        write!(
-            self.terminal,
-            "{}{}{}",
-            cursor::Goto(text_x, self.hbar_row-2),
-            color::Fg(color::Blue),
-            results_text
-        )?;
-
-        if num_results > 1 {
-            // Print the "Next Page →" aligned to the right
-            let right_text = "Next Page → ";
-            let right_x = width.saturating_sub(right_text.len() as u16);
-            write!(
-                self.terminal,
-                "{}{}{}{}{}",
-                cursor::Goto(right_x, self.hbar_row-2),
-                color::Fg(color::Green),
-                Self::style("highlight"),
-                right_text,
-                Self::style("reset")
-            )?;
+        self.text_pane().url = Some(url.to_string());
+        let _ = self.draw();
+    }
 
-            // Move cursor to navigate search output
-            write!(self.terminal, "{}", cursor::Goto(right_x, self.hbar_row-2))?;
+    /// Wrapped message rows and how many of them fit in the output pane
+    fn message_window(&self) -> (usize, usize) {
+        let (area, pane) = match (self.tui.size(), &self.screen.pane) {
+            (Ok(size), Pane::Text(pane)) => (screen_layout(size)[1], pane),
+            _ => return (0, 0),
+        };
+        let body = text_pane_layout(area, pane).1;
+        if let Some(help) = &pane.help {
+            let (rect, rows) = help_box(body, help);
+            return (rows.len(), message_page(rows.len(), rect.height.saturating_sub(2) as usize));
         }
-        self.terminal.flush()?;
-
-        Ok(())
+        let msg_area = message_area(body);
+        let rows = message_rows(pane, msg_area.width as usize, &self.screen.theme).lines.len();
+        (rows, message_page(rows, msg_area.height as usize))
     }
 
-    pub fn navigate_search_output(&mut self) -> Result<i16, Box<dyn std::error::Error>> {
-        let mut count = 0;
-        // Start reading events from the terminal
-        for event in io::stdin().events() {
-            match event? {
-                Event::Key(Key::Char('\n')) => {
-                    count += 1;
-                    break;
-                }
-                Event::Key(Key::Right) => {
-                    count += 1;
-                    break;
-                }
-                Event::Key(Key::Down) => {
-                    count += 1;
-                    break;
-                }
-                Event::Key(Key::Char('\t')) => {
-                    count = 0;
-                    break;
-                }
-                Event::Key(Key::Backspace) => {
-                    count -= 1;
-                    break;
+    /// Scroll text output that is longer than the pane. Quit or Open hand
+    /// back to the prompt; any other typed character is kept and starts the
+    /// next command line.
+    pub fn scroll_output(&mut self) -> io::Result<()> {
+        let (rows, page) = self.message_window();
+        if rows <= page {
+            return Ok(());
+        }
+        self.screen.status = Status::Info(self.screen.keymap.scroll_hint());
+        self.draw()?;
+
+        while let Some(event) = self.read_event(None)? {
+            // Recomputed each key, a resize changes both
+            let (rows, page) = self.message_window();
+            let max_scroll = rows.saturating_sub(page);
+            let scroll = self.text_pane().scroll.min(max_scroll);
+            let (action, key) = match event {
+                Event::Key(key) => (self.screen.keymap.action(key), key),
+                Event::Mouse(mouse) => {
+                    if let Some(note) = self.click_link(mouse) {
+                        self.screen.status = Status::Info(note);
+                        self.draw()?;
+                        continue;
+                    }
+                    (self.mouse_action(mouse), Key::Null)
                 }
-                Event::Key(Key::Left) => {
-                    count -= 1;
+                _ => continue,
+            };
+            let scroll = match (action, key) {
+                (Some(Action::Next), _) => (scroll + 1).min(max_scroll),
+                (Some(Action::Prev), _) => scroll.saturating_sub(1),
+                (Some(Action::PageDown), _) => (scroll + page).min(max_scroll),
+                (Some(Action::PageUp), _) => scroll.saturating_sub(page),
+                (Some(Action::First), _) => 0,
+                (Some(Action::Last), _) => max_scroll,
+                (Some(Action::Quit), _) | (Some(Action::Open), _) => break,
+                (_, Key::Char(_)) | (_, Key::Alt(_)) => {
+                    self.push_back(&event);
                     break;
                 }
-                _ => {}
-            }
+                _ => continue,
+            };
+            self.text_pane().scroll = scroll;
+            self.draw()?;
         }
 
-        self.terminal.flush()?;
+        self.screen.status = Status::Info(String::new());
+        self.draw()
+    }
 
-        Ok(count)
+    /// List of all results with a detail pane for the selected one
+    pub fn show_results(&mut self, query: &str, result: &SearchResult) -> io::Result<()> {
+        self.screen.pane = Pane::Results { query: query.to_string(), result: result.clone() };
+        self.draw()
     }
 
-    fn draw_horizontal_bar(&mut self, width: u16) -> Result<(), Box<dyn std::error::Error>> {
-        write!(self.terminal, "\x1b[48;5;12m")?; // Set background to light blue
-        for _ in 0..width {
-            write!(self.terminal, " ")?; // Fill the line with spaces
+    /// Next pager action, keys without a binding are skipped. Clicking a
+    /// result selects it, clicking the selected one opens it.
+    pub fn navigate_search_output(&mut self, result: &mut SearchResult) -> Result<Action, Box<dyn std::error::Error>> {
+        while let Some(event) = self.read_event(None)? {
+            if tab_key(&event).is_some() {
+                self.push_back(&event);
+                return Ok(Action::Quit);
+            }
+            let action = match event {
+                Event::Key(key) => self.screen.keymap.action(key),
+                Event::Mouse(mouse) => match (self.clicked_result(mouse), self.mouse_action(mouse)) {
+                    (Some(index), _) if index == result.index() => Some(Action::Open),
+                    (Some(index), _) => {
+                        result.select(index);
+                        if let Pane::Results { result: shown, .. } = &mut self.screen.pane {
+                            shown.select(index);
+                        }
+                        self.draw()?;
+                        None
+                    }
+                    (None, action) => action,
+                },
+                _ => None,
+            };
+            if let Some(action) = action {
+                return Ok(action);
+            }
         }
-        Ok(())
+        Ok(Action::Quit)
     }
 
-    pub fn show_qvault_screen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        //writeln!(self.terminal, "{}", msg)?;
-        // Get the terminal size (columns and rows)
-        let (width, height) = termion::terminal_size()?;
-
-        // Calculate the row position near the bottom
-        self.hbar_row = height - 1;
-        self.input_row = self.hbar_row + 1;
-
-        // Move cursor to the calculated row position and column 1 (start of the line)
-        write!(
-            self.terminal,
-            "{}{}{}",
-            termion::clear::All,            // Clear the screen
-            cursor::Goto(1, self.hbar_row), // Move cursor
-            "\x1b[48;5;12m" // Set the background to light blue (color code 12 in 256 palette)
-        )?;
-        // Create a horizontal line of 80 characters of light blue (adjust the width if needed)
-        for _ in 0..width {
-            write!(self.terminal, " ")?; // Print spaces to fill the line
-        }
-
-        // Reset the color to default
-        write!(self.terminal, "{}{}", "\x1b[0m", cursor::Show)?;
-
-        // Move the cursor to the next line but go to column 1 (column 0 in Rust)
-        write!(
-            self.terminal,
-            "{}{}",
-            cursor::Down(1),
-            cursor::Goto(1, self.input_row)
-        )?;
-
-        self.flush()?;
+    /// Result under a left click in the result list
+    fn clicked_result(&self, mouse: MouseEvent) -> Option<usize> {
+        let (x, y) = match mouse {
+            MouseEvent::Press(MouseButton::Left, x, y) => (x.saturating_sub(1), y.saturating_sub(1)),
+            _ => return None,
+        };
+        let count = match &self.screen.pane {
+            Pane::Results { result, .. } => result.count(),
+            _ => return None,
+        };
+        let output = screen_layout(self.tui.size().ok()?)[1];
+        result_at(output, self.screen.results_offset, x, y).filter(|index| *index < count)
+    }
 
+    /// Put text on the system clipboard with OSC 52. Works over SSH; inside
+    /// tmux the sequence is wrapped so tmux passes it on.
+    pub fn copy_to_clipboard(&mut self, text: &str) -> io::Result<()> {
+        let osc = format!("\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text));
+        let backend = self.tui.backend_mut();
+        if std::env::var_os("TMUX").is_some() {
+            write!(backend, "\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))?;
+        } else {
+            write!(backend, "{}", osc)?;
+        }
+        Backend::flush(backend)
+    }
+
+    /// Start screen: empty output, bar and prompt
+    pub fn show_qvault_screen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.tui.clear()?;
+        self.screen.pane = Pane::Text(TextPane::default());
+        self.screen.status = Status::Info(String::new());
+        self.draw()?;
         Ok(())
     }
 
     pub fn clear_output_screen(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let (width, height) = termion::terminal_size()?; // Get terminal size
-        self.hbar_row = height - 1; // Horizontal bar at the second-to-last row
-        self.input_row = self.hbar_row + 1; // Input row below the bar
-
-        // Clear the screen and move cursor to the horizontal bar row
-        write!(
-            self.terminal,
-            "{}{}",
-            clear::All,
-            cursor::Goto(1, self.hbar_row)
-        )?;
-
-        // Draw the horizontal bar with a blue background
-        self.draw_horizontal_bar(width)?;
-
-        // Reset terminal colors and position cursor for input
-        write!(
-            self.terminal,
-            "{}{}{}",
-            "\x1b[0m",             // Reset color to default
-            cursor::Show,          // Show the cursor
-            cursor::Goto(1, self.input_row) // Move to input row
-        )?;
-
-        self.flush()?; // Ensure everything is written to the terminal
-
+        self.screen.pane = Pane::Text(TextPane::default());
+        self.draw()?;
         Ok(())
     }
 
     pub fn show_prompt(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        // Move to the input row and clear the current line
-        write!(
-            self.terminal,
-            "{}{}\u{1F50D}>",                // Clear the line and display the prompt
-            cursor::Goto(1, self.input_row), // Move to input row, column 1
-            clear::CurrentLine               // Clear the entire current line
-        )?;
-        self.flush()?;
-        self.input_col = 3;
-
+        // Keep what was typed ahead while the last command ran
+        if !self.screen.typing {
+            self.screen.prompt.clear();
+            self.screen.caret = 0;
+        }
+        self.screen.typing = true;
+        self.draw()?;
         Ok(())
     }
 
     pub fn write_bar_message(&mut self, msg: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Save the current cursor position
-        write!(self.terminal, "{}", cursor::Save)?;
-
-        // Move the cursor to the horizontal bar row and the calculated column
-        write!(
-            self.terminal,
-            "{}{}\u{1F30D}  \u{1F4DA}  {}{}",
-            cursor::Goto(1, self.hbar_row), // Move cursor
-            "\x1b[1;37m\x1b[48;5;12m",      // Set bold white text with light blue background
-            msg,                            // Write the message
-            "\x1b[0m"                       // Reset text style
-        )?;
-
-        //println!("Search \u{1F50D}");
-        // Get the terminal size (width and height)
-        let (width, _) = termion::terminal_size()?;
-
-        // Move the cursor to the last column of the first row
-        write!(
-            self.terminal,
-            "{}\u{1F5C4}", // Unicode for file cabinet emoji
-            cursor::Goto(width - 2, self.hbar_row)
-        )?;
-
-        // Restore the cursor to its original position
-        write!(self.terminal, "{}", cursor::Restore)?;
-
-        self.terminal.flush()?;
+        self.screen.status = Status::Info(msg.to_string());
+        self.draw()?;
         Ok(())
     }
 
-    fn tui_draw_box(&mut self, x: u16, y: u16, width: u16, height: u16) {
-        // Unicode codes for box-drawing characters
-        let horizontal = "\u{2500}"; // ─
-        let vertical = "\u{2502}";   // │
-        let top_left = "\u{250C}";   // ┌
-        let top_right = "\u{2510}";  // ┐
-        let bottom_left = "\u{2514}";// └
-        let bottom_right = "\u{2518}";// ┘
-
-        // Draw top and bottom borders
-        for i in 0..width {
-            write!(self.terminal, "{}{}", cursor::Goto(x + i, y), horizontal).unwrap(); // Top border
-            write!(self.terminal, "{}{}", cursor::Goto(x + i, y + height - 1), horizontal).unwrap(); // Bottom border
-        }
-
-        // Draw left and right borders
-        for i in 0..height {
-            write!(self.terminal, "{}{}", cursor::Goto(x, y + i), vertical).unwrap(); // Left border
-            write!(self.terminal, "{}{}", cursor::Goto(x + width - 1, y + i), vertical).unwrap(); // Right border
-        }
-
-        // Draw corners
-        write!(self.terminal, "{}{}", cursor::Goto(x, y), top_left).unwrap(); // Top-left corner
-        write!(self.terminal, "{}{}", cursor::Goto(x + width - 1, y), top_right).unwrap(); // Top-right corner
-        write!(self.terminal, "{}{}", cursor::Goto(x, y + height - 1), bottom_left).unwrap(); // Bottom-left corner
-        write!(self.terminal, "{}{}", cursor::Goto(x + width - 1, y + height - 1), bottom_right).unwrap(); // Bottom-right corner
-
-        self.terminal.flush().unwrap();
-    }
-
-    /// Resets terminal styles
-    fn reset_code() -> &'static str {
-        "\x1b[0m"
-    }
-
-    /// Returns the ANSI color code string
-    fn style(style: &str) -> &'static str {
-        match style {
-            "cyan" => "\x1b[36m",
-            "yellow" => "\x1b[33m",
-            "blue_bg" => "\x1b[44m",
-            "green" => "\x1b[32m",
-            "red" => "\x1b[31m",
-            "bold" => "\x1b[1m",           // Bold text
-            "dim" => "\x1b[2m",            // Dim text
-            "highlight" => "\x1b[7m",      // Reverse video (background/foreground swap)
-            "reset" => "\x1b[0m",          // Reset all styles
-            _ => "\x1b[0m",                // Default to reset
-        }
+    /// Like write_bar_message, but red on the bar so it stands out as an error
+    pub fn write_bar_error(&mut self, msg: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.screen.status = Status::Error(msg.to_string());
+        self.draw()?;
+        Ok(())
     }
 
-    fn render_settings_ui(
-        &mut self,
-        username: &str,
-        password: &str,
-        active_field: usize,
-    ) -> io::Result<()> {
-        //write!(self.terminal, "{}{}", clear::All, cursor::Goto(1, 1))?;
-        //self.clear_output_screen();
-
-        // Title
-        /*
-        write!(
-            self.terminal,
-            "{}{}Login Form{}",
-            Self::style("bold"),
-            cursor::Goto(10, 1),
-            Self::style("reset")
-        )?;
-        */
-
-        // Username field
-        //self.draw_box(18, 5, 33, 3, active_field == 0)?;
-        self.tui_draw_box(18, 5, 33, 3);
-        write!(
-            self.terminal,
-            "{}{}{}Brave API Key:{}{}",
-            cursor::Goto(19, 5),
-            Self::style(if active_field == 0 { "highlight" } else { "dim" }),
-            Self::style("cyan"),
-            Self::style("reset"),
-            Self::style("reset"),
-        )?;
-        write!(
-            self.terminal,
-            "{}{: <20}",
-            cursor::Goto(19, 6),
-            username,
-        )?;
-
-        // Password field
-        //self.draw_box(18, 9, 33, 3, active_field == 1)?;
-        self.tui_draw_box(18, 9, 33, 3);
-        write!(
-            self.terminal,
-            "{}{}{}OpenAI API Key:{}{}",
-            cursor::Goto(19, 9),
-            Self::style(if active_field == 1 { "highlight" } else { "dim" }),
-            Self::style("cyan"),
-            Self::style("reset"),
-            Self::style("reset"),
-        )?;
-        write!(
-            self.terminal,
-            "{}{: <20}",
-            cursor::Goto(19, 10),
-            password
-        )?;
-
-        // Cancel button
-        write!(
-            self.terminal,
-            "{}{}[ {}Cancel{} ]{}",
-            cursor::Goto(10, 15),
-            Self::style(if active_field == 2 { "highlight" } else { "dim" }),
-            Self::style("yellow"),
-            Self::style("reset"),
-            Self::style("reset")
-        )?;
-
-        // Submit button
-        write!(
-            self.terminal,
-            "{}{}[ {}Submit{} ]{}",
-            cursor::Goto(45, 15),
-            Self::style(if active_field == 3 { "highlight" } else { "dim" }),
-            Self::style("green"),
-            Self::style("reset"),
-            Self::style("reset")
-        )?;
-
-        self.terminal.flush()
+    fn settings_pane(&mut self, brave: &str, openai: &str, active: usize, note: Option<String>) -> io::Result<()> {
+        self.screen.pane = Pane::Settings { brave: brave.to_string(), openai: openai.to_string(), active, note };
+        self.draw()
     }
 
     pub fn tui_show_settings(&mut self) -> io::Result<()> {
-        // Set up raw mode for terminal
-        let stdin = io::stdin();
-
         // Input fields and states
         let mut username = String::new();
         let mut password = String::new();
         let mut active_field = 0; // 0: username, 1: password, 2: cancel, 3: submit
 
-        self.render_settings_ui(&username, &password, active_field)?;
+        self.settings_pane(&username, &password, active_field, None)?;
 
         // Input loop
-        for key in stdin.keys() {
-            match key? {
+        while let Some(event) = self.read_event(None)? {
+            let key = match event {
+                Event::Key(key) => key,
+                _ => continue,
+            };
+            match key {
                 Key::Char('\t') => {
                     // Tab to switch fields
                     active_field = (active_field + 1) % 4;
@@ -472,27 +631,20 @@ impl QvaultTerminal {
                 Key::Char('\n') => {
                     // Enter to submit
                     if active_field == 3 {
-                        write!(
-                            self.terminal,
-                            "{}{}\nSubmitted! Username: {}, Password: {}\nPress any key to exit.",
-                            cursor::Goto(1, 15),
-                            clear::AfterCursor,
-                            username,
-                            password
-                        )?;
-                        self.terminal.flush()?;
+                        qvault_log::register_secret(&username);
+                        qvault_log::register_secret(&password);
+                        let note = format!(
+                            "Submitted! Brave key: {}, OpenAI key: {}",
+                            qvault_log::mask_secret(&username),
+                            qvault_log::mask_secret(&password)
+                        );
+                        self.settings_pane(&username, &password, active_field, Some(note))?;
                         break;
                     }
 
                     // Enter to cancel
                     if active_field == 2 {
-                        write!(
-                            self.terminal,
-                            "{}{}\nCancelled!!",
-                            cursor::Goto(1, 15),
-                            clear::AfterCursor
-                        )?;
-                        self.terminal.flush()?;
+                        self.settings_pane(&username, &password, active_field, Some("Cancelled!!".to_string()))?;
                         break;
                     }
                 }
@@ -515,85 +667,987 @@ impl QvaultTerminal {
                 Key::Esc => break, // Exit on Esc key
                 _ => {}
             }
-            self.render_settings_ui(&username, &password, active_field)?;
+            self.settings_pane(&username, &password, active_field, None)?;
         }
 
         Ok(())
     }
 
+    /// Help box over the output, scrolled like long messages when it does not fit
     pub fn tui_show_help(&mut self, help: Vec<String>) {
-        // Get terminal size
-        let (term_width, term_height) = termion::terminal_size().unwrap();
+        let pane = self.text_pane();
+        pane.help = Some(help);
+        pane.scroll = 0;
+        let _ = self.draw();
+    }
+
+    fn render_log_view(
+        &mut self,
+        path: &str,
+        lines: &[String],
+        filter: &qvault_log::LogFilter,
+        scroll: usize,
+        follow: bool,
+        editing: Option<&str>,
+    ) -> io::Result<()> {
+        // Header: file, active filters and follow state
+        let title = format!(
+            "LOG {}  level<={}  filter:{}  {}",
+            path,
+            filter.min_level,
+            if filter.needle.is_empty() { "-" } else { &filter.needle },
+            if follow { "[follow]" } else { "" },
+        );
 
-        // Box dimensions
-        let box_width = 60;
-        let box_height = 15;
+        // Footer doubles as the filter editor
+        let footer = match editing {
+            Some(buf) => format!("filter: {}", buf),
+            None => format!("{}  l level  / filter  f follow", self.screen.keymap.scroll_hint()),
+        };
+        let lines: Vec<String> = lines.iter().filter(|l| filter.matches(l)).cloned().collect();
+        // Scrolled to the end stays at the end when a resize changes the page
+        let scroll = if scroll >= lines.len().saturating_sub(self.log_page()) { usize::MAX } else { scroll };
+        self.screen.pane = Pane::Log { title, lines, scroll, footer };
+        self.draw()
+    }
 
-        // Calculate top-left corner to center the box
-        let x = (term_width - box_width) / 2;
-        let y = (term_height - box_height) / 2;
+    /// Log lines that fit in the output pane, below the header and above the footer
+    fn log_page(&self) -> usize {
+        self.output_height().saturating_sub(2) as usize
+    }
 
-        // Draw the rectangle
-        self.tui_draw_box(x, y, box_width, box_height);
-        let mut line = 1;
+    /// Scrollable view of the log file. Follow mode tails new lines as they are written.
+    pub fn tui_show_log(&mut self, path: &str, mut filter: qvault_log::LogFilter) -> io::Result<()> {
+        let mut file = File::open(path)?;
+        let mut lines: Vec<String> = Vec::new();
+        let mut offset = read_new_lines(&mut file, 0, &mut lines)?;
 
-        for s in help {
-            writeln!(self.terminal, "{}{}", cursor::Goto(x+5, y+line+3), s);
-            line += 1;
+        let mut page = self.log_page();
+        let visible = |lines: &[String], filter: &qvault_log::LogFilter| {
+            lines.iter().filter(|l| filter.matches(l)).count()
+        };
+        let mut scroll = visible(&lines, &filter).saturating_sub(page);
+        let mut follow = false;
+        let mut editing: Option<String> = None;
+
+        self.render_log_view(path, &lines, &filter, scroll, follow, editing.as_deref())?;
+        loop {
+            let event = match self.read_event(Some(Duration::from_millis(250)))? {
+                Some(event) => event,
+                None if follow => {
+                    page = self.log_page();
+                    let before = lines.len();
+                    offset = read_new_lines(&mut file, offset, &mut lines)?;
+                    if lines.len() != before {
+                        scroll = visible(&lines, &filter).saturating_sub(page);
+                        self.render_log_view(path, &lines, &filter, scroll, follow, editing.as_deref())?;
+                    }
+                    continue;
+                }
+                None => continue,
+            };
+            // The window may have been resized while waiting
+            let at_end = scroll >= visible(&lines, &filter).saturating_sub(page);
+            page = self.log_page();
+            if at_end {
+                scroll = visible(&lines, &filter).saturating_sub(page);
+            }
+            let max_scroll = visible(&lines, &filter).saturating_sub(page);
+
+            if let Some(buf) = editing.as_mut() {
+                match event {
+                    Event::Key(Key::Char('\n')) => {
+                        filter.needle = std::mem::take(buf);
+                        editing = None;
+                        scroll = visible(&lines, &filter).saturating_sub(page);
+                    }
+                    Event::Key(Key::Esc) => editing = None,
+                    Event::Key(Key::Backspace) => {
+                        buf.pop();
+                    }
+                    Event::Key(Key::Char(c)) => buf.push(c),
+                    _ => {}
+                }
+            } else {
+                let (key, action) = match event {
+                    Event::Key(key) => (key, self.screen.keymap.action(key)),
+                    Event::Mouse(mouse) => (Key::Null, self.mouse_action(mouse)),
+                    _ => continue,
+                };
+                match key {
+                    Key::Char('l') => {
+                        filter.cycle_level();
+                        scroll = visible(&lines, &filter).saturating_sub(page);
+                    }
+                    Key::Char('/') => editing = Some(filter.needle.clone()),
+                    Key::Char('f') => {
+                        follow = !follow;
+                        if follow {
+                            offset = read_new_lines(&mut file, offset, &mut lines)?;
+                            scroll = visible(&lines, &filter).saturating_sub(page);
+                        }
+                    }
+                    _ => match action {
+                        Some(Action::Quit) | Some(Action::Open) => break,
+                        Some(Action::Next) => {
+                            follow = false;
+                            scroll = (scroll + 1).min(max_scroll);
+                        }
+                        Some(Action::Prev) => {
+                            follow = false;
+                            scroll = scroll.saturating_sub(1);
+                        }
+                        Some(Action::PageDown) => scroll = (scroll + page).min(max_scroll),
+                        Some(Action::PageUp) => {
+                            follow = false;
+                            scroll = scroll.saturating_sub(page);
+                        }
+                        Some(Action::First) => {
+                            follow = false;
+                            scroll = 0;
+                        }
+                        Some(Action::Last) => scroll = max_scroll,
+                        _ => {}
+                    },
+                }
+            }
+            self.render_log_view(path, &lines, &filter, scroll, follow, editing.as_deref())?;
         }
-    }
 
-    pub fn tui_get_input(&mut self) -> Result<String, Box<dyn std::error::Error>> {
-        // Create a buffer to store the user input
-        let mut buffer = String::new();
+        Ok(())
+    }
 
-        // Inform the user
-        //write!(self.terminal, "{}Reading input in raw mode, press 'q' to quit{}",     cursor::Goto(1, 1), cursor::Hide)?;
-        self.terminal.flush()?;
+    /// Read one line at the prompt. Tab completes a `/command` from `completions`.
+    /// Left/Right or a click in the input line move the caret; a click on a
+    /// link in the output opens it. Text typed ahead during the last request
+    /// is already in the line. Alt-1..Alt-9 come back as `/tab N`.
+    pub fn tui_get_input(&mut self, completions: &[String]) -> Result<String, Box<dyn std::error::Error>> {
+        self.screen.typing = true;
+        self.draw()?;
 
         // Start reading events from the terminal
-        for event in io::stdin().events() {
-            match event? {
-                Event::Key(Key::Char('\n')) => break, // Stop at Enter key
-                Event::Key(Key::Char(c)) => {
-                    // Add character to the buffer
-                    buffer.push(c);
+        while let Some(event) = self.read_event(None)? {
+            if event == Event::Key(Key::Char('\n')) {
+                break; // Stop at Enter key
+            }
+            // Alt-N runs /tab N, what was typed stays for after the switch
+            if let Some(n) = tab_key(&event) {
+                return Ok(format!("/tab {}", n));
+            }
+            self.edit_prompt(event, completions);
+            self.draw()?;
+        }
+
+        // The line stays visible while the command runs, without the cursor
+        self.screen.typing = false;
+        self.draw()?;
+
+        // Return the collected input
+        Ok(self.screen.prompt.clone())
+    }
+
+    /// Apply one key or click to the input line
+    fn edit_prompt(&mut self, event: Event, completions: &[String]) {
+        let (buffer, caret) = (&mut self.screen.prompt, &mut self.screen.caret);
+        match event {
+            Event::Key(Key::Char('\t')) => {
+                let matches = complete_command(buffer, completions);
+                match matches.len() {
+                    0 => {}
+                    1 => *buffer = format!("{} ", matches[0]),
+                    _ => {
+                        *buffer = common_prefix(&matches);
+                        self.screen.status = Status::Info(matches.join("  "));
+                    }
+                }
+                *caret = buffer.len();
+            }
+            Event::Key(Key::Char(c)) => {
+                // Add character at the caret
+                buffer.insert(*caret, c);
+                *caret += c.len_utf8();
+            }
+            Event::Key(Key::Backspace) => {
+                // Remove the character before the caret
+                if let Some(c) = buffer[..*caret].chars().next_back() {
+                    *caret -= c.len_utf8();
+                    buffer.remove(*caret);
                 }
-                Event::Key(Key::Backspace) => {
-                    // Remove the last character from the buffer
-                    buffer.pop();
+            }
+            Event::Key(Key::Left) => *caret -= buffer[..*caret].chars().next_back().map_or(0, char::len_utf8),
+            Event::Key(Key::Right) => *caret += buffer[*caret..].chars().next().map_or(0, char::len_utf8),
+            Event::Key(Key::Home) | Event::Key(Key::Ctrl('a')) => *caret = 0,
+            Event::Key(Key::End) | Event::Key(Key::Ctrl('e')) => *caret = buffer.len(),
+            Event::Mouse(mouse) => {
+                if let Some(note) = self.click_link(mouse) {
+                    self.screen.status = Status::Info(note);
+                } else if let Some(at) = self.clicked_caret(mouse) {
+                    self.screen.caret = at;
                 }
-                _ => {}
             }
-
-            // Display the current input in the terminal
-            write!(
-                self.terminal,
-                "{}{}",
-                cursor::Goto(self.input_col, self.input_row),
-                buffer
-            )?;
-            self.terminal.flush()?;
+            _ => {}
         }
+    }
 
-        // Show the final input once user presses 'q'
-        //write!(self.terminal, "{}You entered: {}", cursor::Goto(1, 4), buffer)?;
-        self.terminal.flush()?;
+    /// Caret position for a left click in the input line
+    fn clicked_caret(&self, mouse: MouseEvent) -> Option<usize> {
+        let (x, y) = match mouse {
+            MouseEvent::Press(MouseButton::Left, x, y) => (x.saturating_sub(1), y.saturating_sub(1)),
+            _ => return None,
+        };
+        let input = screen_layout(self.tui.size().ok()?)[3];
+        if y != input.y {
+            return None;
+        }
+        let buffer = &self.screen.prompt;
+        let prompt_width = Span::raw(self.screen.theme.glyphs.prompt).width() as u16;
+        let start = input_start(buffer, self.screen.caret, input_room(input, prompt_width));
+        let mut column = x.saturating_sub(input.x + prompt_width) as usize;
+        for (i, c) in buffer[start..].char_indices() {
+            let width = Span::raw(c.to_string()).width();
+            // Past the middle of a character puts the caret after it
+            if column * 2 < width {
+                return Some(start + i);
+            }
+            column = column.saturating_sub(width);
+        }
+        Some(buffer.len())
+    }
 
-        // Return the collected input
-        Ok(buffer)
+    /// Wait for a background request. The status bar shows a spinner and
+    /// the elapsed time, Esc cancels, and keys typed meanwhile start the
+    /// next command line. Returns `None` when cancelled.
+    pub fn wait_for<T>(&mut self, label: &str, mut task: JoinHandle<T>) -> io::Result<Option<T>> {
+        let start = Instant::now();
+        loop {
+            if task.is_finished() {
+                return qvault_task::block_on(&mut task)
+                    .map(Some)
+                    .map_err(|err| io::Error::other(format!("{} failed: {}", label, err)));
+            }
+            let elapsed = start.elapsed();
+            let spinner = self.screen.theme.glyphs.spinner;
+            let frame = spinner[(elapsed.as_millis() / SPINNER_TICK.as_millis()) as usize % spinner.len()];
+            self.screen.status =
+                Status::Info(format!("{} {} {:.1}s  Esc cancels", frame, label, elapsed.as_secs_f32()));
+            self.draw()?;
+
+            match self.read_event(Some(SPINNER_TICK))? {
+                Some(Event::Key(Key::Esc)) => {
+                    task.abort();
+                    qvault_log::log_info("Request cancelled: ", format_args!("{} after {:?}", label, elapsed));
+                    return Ok(None);
+                }
+                // One command runs at a time, Enter waits for this one
+                Some(Event::Key(Key::Char('\n'))) | None => {}
+                Some(event) => {
+                    // The line of the running command makes way for the next one
+                    if !self.screen.typing {
+                        self.screen.typing = true;
+                        self.screen.prompt.clear();
+                        self.screen.caret = 0;
+                    }
+                    self.edit_prompt(event, &[]);
+                }
+            }
+        }
     }
 
     // Method to shut down and restore terminal settings
     pub fn shutdown(mut self) {
-        let _ = write!(
-            self.terminal,
-            "{}{}{}",
-            termion::clear::All,
-            cursor::Show,
-            cursor::Goto(1, 1)
+        let _ = self.tui.clear();
+        let _ = self.tui.show_cursor();
+        let backend = self.tui.backend_mut();
+        let _ = write!(backend, "{}", cursor::Goto(1, 1));
+        let _ = Backend::flush(backend);
+    }
+}
+
+/// Draw every region of the screen. Returns where the OSC 8 link texts
+/// went and where the input caret belongs while typing.
+fn draw_screen<B: Backend>(f: &mut Frame<B>, screen: &mut Screen) -> (Vec<LinkSpot>, Option<(u16, u16)>) {
+    let chunks = screen_layout(f.size());
+    let (header, output, status, input) = (chunks[0], chunks[1], chunks[2], chunks[3]);
+
+    // Header
+    let theme = &screen.theme;
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    f.render_widget(Paragraph::new(Span::styled(" QueryVault", bold)), header);
+    f.render_widget(
+        Paragraph::new(Span::styled("/help for commands ", Style::default().fg(theme.dim))).alignment(Alignment::Right),
+        header,
+    );
+
+    // Output
+    let mut links = Vec::new();
+    match &screen.pane {
+        Pane::Text(pane) => links = draw_text_pane(f, output, pane, theme),
+        Pane::Results { query, result } => {
+            draw_results(f, output, query, result, &mut screen.results_offset, theme, &screen.keymap)
+        }
+        Pane::Settings { brave, openai, active, note } => draw_settings(f, output, brave, openai, *active, note.as_deref(), theme),
+        Pane::Log { title, lines, scroll, footer } => draw_log(f, output, title, lines, *scroll, footer, theme),
+    }
+
+    // Status bar
+    let glyphs = theme.glyphs;
+    let bar = if theme.color { Style::default().bg(theme.bar_bg) } else { Style::default().add_modifier(Modifier::REVERSED) };
+    let text = match &screen.status {
+        Status::Info(msg) => Span::styled(format!("{}{}", glyphs.bar, msg), bar.fg(theme.bar_fg).add_modifier(Modifier::BOLD)),
+        Status::Error(msg) => Span::styled(format!("{}{}", glyphs.error, msg), bar.fg(theme.error).add_modifier(Modifier::BOLD)),
+    };
+    f.render_widget(Paragraph::new(text).style(bar), status);
+    let mut end_width = Span::raw(glyphs.bar_end).width() as u16 + 1;
+    if let (Status::Info(_), false) = (&screen.status, glyphs.bar_end.is_empty()) {
+        f.render_widget(
+            Paragraph::new(glyphs.bar_end).style(bar).alignment(Alignment::Right),
+            Rect { x: status.right().saturating_sub(end_width), width: end_width.min(status.width), ..status },
         );
-        let _ = self.terminal.flush();
-        //self.terminal.show_cursor()?; // Ensure cursor is shown when shutting
+    } else {
+        end_width = 0;
+    }
+
+    // Tab strip at the right end of the bar, once a second tab is in use
+    let tabs = tab_strip(screen);
+    if tabs.len() > 1 || screen.active > 0 {
+        let mut spans = Vec::new();
+        for (n, label) in tabs {
+            let label = format!(" {} {} ", n, clip_to_width(&label, TAB_LABEL_WIDTH));
+            let style = if n == screen.active + 1 {
+                bar.fg(theme.accent).add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            } else {
+                bar.fg(theme.bar_fg)
+            };
+            spans.push(Span::styled(label, style));
+        }
+        let strip = Spans::from(spans);
+        let width = (strip.width() as u16).min(status.width.saturating_sub(end_width));
+        f.render_widget(
+            Paragraph::new(strip).style(bar),
+            Rect { x: status.right().saturating_sub(end_width + width), width, ..status },
+        );
+    }
+
+    // Input line, scrolled so the caret stays in view
+    let prompt_width = Span::raw(glyphs.prompt).width() as u16;
+    let room = input_room(input, prompt_width);
+    let caret = screen.caret.min(screen.prompt.len());
+    let start = input_start(&screen.prompt, caret, room);
+    let shown = clip_to_width(&screen.prompt[start..], room);
+    f.render_widget(Paragraph::new(format!("{}{}", glyphs.prompt, shown)), input);
+    let before = Span::raw(&screen.prompt[start..caret]).width() as u16;
+    let caret = screen.typing.then(|| (input.x + prompt_width + before, input.y));
+    if let Some((x, y)) = caret {
+        f.set_cursor(x, y);
+    }
+    if glyphs.ascii {
+        f.render_widget(AsciiBorders, f.size());
+    }
+
+    (links, caret)
+}
+
+/// Tab number, from 1, and label of each tab that has output, plus the
+/// active tab even when empty
+fn tab_strip(screen: &Screen) -> Vec<(usize, String)> {
+    (0..screen.tabs.len())
+        .filter_map(|i| {
+            let pane = if i == screen.active { &screen.pane } else { &screen.tabs[i].pane };
+            match pane.label() {
+                Some(label) => Some((i + 1, label)),
+                None if i == screen.active => Some((i + 1, "new".to_string())),
+                None => None,
+            }
+        })
+        .collect()
+}
+
+/// Tab number for Alt-1..Alt-9
+fn tab_key(event: &Event) -> Option<usize> {
+    match event {
+        Event::Key(Key::Alt(c @ '1'..='9')) => c.to_digit(10).map(|n| n as usize),
+        _ => None,
+    }
+}
+
+/// Columns for input text after the prompt, one is kept for the caret
+fn input_room(input: Rect, prompt_width: u16) -> usize {
+    input.width.saturating_sub(prompt_width + 1) as usize
+}
+
+/// First byte of `prompt` shown in the input line, late enough that the
+/// text up to the caret fits in `room` columns
+fn input_start(prompt: &str, caret: usize, room: usize) -> usize {
+    let mut start = 0;
+    while Span::raw(&prompt[start..caret]).width() > room {
+        start += prompt[start..].chars().next().map_or(1, char::len_utf8);
+    }
+    start
+}
+
+/// Swaps line drawing characters for ASCII once everything else is drawn,
+/// tui only has line drawing sets for block borders
+struct AsciiBorders;
+
+impl Widget for AsciiBorders {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                let ascii = match cell.symbol.as_str() {
+                    "\u{2500}" | "\u{2501}" | "\u{2550}" => "-",
+                    "\u{2502}" | "\u{2503}" | "\u{2551}" => "|",
+                    "\u{250C}" | "\u{2510}" | "\u{2514}" | "\u{2518}" | "\u{256D}" | "\u{256E}" | "\u{256F}" | "\u{2570}"
+                    | "\u{251C}" | "\u{2524}" | "\u{252C}" | "\u{2534}" | "\u{253C}" => "+",
+                    _ => continue,
+                };
+                cell.set_symbol(ascii);
+            }
+        }
+    }
+}
+
+/// Title, messages, link and help box of ordinary output
+fn draw_text_pane<B: Backend>(f: &mut Frame<B>, area: Rect, pane: &TextPane, theme: &Theme) -> Vec<LinkSpot> {
+    let mut links = Vec::new();
+    if area.height == 0 {
+        return links;
+    }
+    let (title_row, body, url_row) = text_pane_layout(area, pane);
+
+    if let (Some(title), Some(row)) = (&pane.title, title_row) {
+        let title = if title.is_empty() { "SEARCH RESULTS".to_string() } else { title.to_uppercase() };
+        let decoration = theme.glyphs.title;
+        let full_title = format!("{} {} {}", decoration, title, decoration);
+        f.render_widget(Paragraph::new(Span::styled(full_title, Style::default().add_modifier(Modifier::BOLD))).alignment(Alignment::Center), row);
+    }
+
+    if let (Some(url), Some(row)) = (&pane.url, url_row) {
+        let label = theme.glyphs.url;
+        let label_width = Span::raw(label).width() as u16;
+        let fits: String = clip_to_width(url, row.width.saturating_sub(label_width) as usize);
+        f.render_widget(Paragraph::new(Spans::from(vec![Span::raw(label), Span::styled(fits.clone(), qvault_markdown::link_style(theme))])), row);
+        links.push((row.x + label_width, row.y, url.clone(), fits));
+    }
+
+    if !pane.messages.is_empty() {
+        let msg_area = message_area(body);
+        let Rendered { lines: rows, links: row_links } = message_rows(pane, msg_area.width as usize, theme);
+        let page = message_page(rows.len(), msg_area.height as usize);
+        let start = pane.scroll.min(rows.len().saturating_sub(page));
+        let lines: Vec<Spans> = rows.iter().skip(start).take(page).cloned().collect();
+        f.render_widget(Paragraph::new(Text::from(lines)), msg_area);
+        for link in row_links.into_iter().filter(|l| (start..start + page).contains(&l.row)) {
+            let y = msg_area.y + (link.row - start) as u16;
+            links.push((msg_area.x + link.col as u16, y, link.url, link.text));
+        }
+
+        // Last row tells where we are when the text does not fit
+        if page < rows.len() {
+            let end = start + page;
+            let indicator = format!(
+                "{}-{}/{} ({}%)",
+                start + 1,
+                end,
+                rows.len(),
+                end * 100 / rows.len()
+            );
+            let row = Rect { y: msg_area.bottom() - 1, height: 1, ..msg_area };
+            f.render_widget(
+                Paragraph::new(Span::styled(indicator, Style::default().add_modifier(Modifier::DIM))).alignment(Alignment::Right),
+                row,
+            );
+        }
+    }
+
+    if let Some(help) = &pane.help {
+        let (rect, rows) = help_box(body, help);
+        let inner = Rect {
+            x: rect.x + 1.min(rect.width),
+            y: rect.y + 1.min(rect.height),
+            width: rect.width.saturating_sub(2),
+            height: rect.height.saturating_sub(2),
+        };
+        let page = message_page(rows.len(), inner.height as usize);
+        let start = pane.scroll.min(rows.len().saturating_sub(page));
+        let lines: Vec<Spans> = rows.iter().skip(start).take(page).map(|l| Spans::from(l.as_str())).collect();
+        f.render_widget(Clear, rect);
+        f.render_widget(Block::default().borders(Borders::ALL), rect);
+        f.render_widget(Paragraph::new(lines), inner);
+
+        // Same indicator as long messages, on the last row inside the box
+        if page < rows.len() && inner.height > 0 {
+            let end = start + page;
+            let indicator = format!("{}-{}/{} ({}%)", start + 1, end, rows.len(), end * 100 / rows.len());
+            let row = Rect { y: inner.bottom() - 1, height: 1, ..inner };
+            f.render_widget(
+                Paragraph::new(Span::styled(indicator, Style::default().add_modifier(Modifier::DIM))).alignment(Alignment::Right),
+                row,
+            );
+        }
+    }
+    links
+}
+
+/// Where the help box goes in the body and its rows, wrapped to fit
+/// inside the borders with a blank row on top. Rows past the box height
+/// are reached by scrolling.
+fn help_box(body: Rect, help: &[String]) -> (Rect, Vec<String>) {
+    let longest = help.iter().map(|l| Span::raw(l.as_str()).width()).max().unwrap_or(0) as u16;
+    let width = (longest + 6).max(60).min(body.width);
+    let text_width = width.saturating_sub(4).max(1) as usize;
+    let mut rows = vec![String::new()];
+    for line in help {
+        rows.extend(wrap_line(line, text_width).into_iter().map(|part| format!("  {}", part)));
+    }
+    let height = (rows.len() as u16 + 2).max(15).min(body.height);
+    let rect = Rect {
+        x: body.x + (body.width - width) / 2,
+        y: body.y + (body.height - height) / 2,
+        width,
+        height,
+    };
+    (rect, rows)
+}
+
+/// Title row, body and URL row of the text pane
+fn text_pane_layout(area: Rect, pane: &TextPane) -> (Option<Rect>, Rect, Option<Rect>) {
+    let mut title_row = None;
+    let mut body = area;
+    if pane.title.is_some() && area.height > 0 {
+        let row = Rect { y: area.y + 1.min(area.height - 1), height: 1, ..area };
+        let top = (row.y + 2).min(area.bottom());
+        body = Rect { y: top, height: area.bottom() - top, ..area };
+        title_row = Some(row);
+    }
+
+    let mut url_row = None;
+    if pane.url.is_some() && body.height > 0 {
+        url_row = Some(Rect { y: body.bottom() - 1, height: 1, ..body });
+        body.height -= 1;
+    }
+    (title_row, body, url_row)
+}
+
+/// Messages sit one row below the title with a blank row under them
+fn message_area(body: Rect) -> Rect {
+    Rect { y: body.y + 1.min(body.height), height: body.height.saturating_sub(2), ..body }
+}
+
+/// Message rows shown at once; one row goes to the scroll indicator when they overflow
+fn message_page(rows: usize, height: usize) -> usize {
+    if rows > height {
+        height.saturating_sub(1).max(1)
+    } else {
+        height
+    }
+}
+
+/// Every message wrapped to `width` columns. The first line of a plain
+/// message gets the bullet, the rest are indented to line up under it.
+/// Markdown is laid out by qvault_markdown with a two column margin.
+fn message_rows(pane: &TextPane, width: usize, theme: &Theme) -> Rendered {
+    let mut rows = Rendered::default();
+    for (_, msg) in &pane.messages {
+        if pane.markdown {
+            let md = qvault_markdown::render(msg, width.saturating_sub(2), theme);
+            rows.links.extend(md.links.into_iter().map(|link| LinkSpan {
+                row: link.row + rows.lines.len(),
+                col: link.col + 2,
+                ..link
+            }));
+            rows.lines.extend(md.lines.into_iter().map(|mut line| {
+                line.0.insert(0, Span::raw("  "));
+                line
+            }));
+            continue;
+        }
+        let bullet = theme.glyphs.bullet;
+        let indent = " ".repeat(Span::raw(bullet).width());
+        for (i, line) in msg.lines().enumerate() {
+            for (j, part) in wrap_line(line, width.saturating_sub(indent.len())).into_iter().enumerate() {
+                let lead = if i == 0 && j == 0 { bullet } else { &indent };
+                rows.lines.push(Spans::from(format!("{}{}", lead, part)));
+            }
+        }
+    }
+    rows
+}
+
+/// Word wrap one line of plain text to `width` display columns
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let cells: Vec<(char, ())> = line.replace('\t', "    ").chars().map(|c| (c, ())).collect();
+    qvault_markdown::wrap(&cells, width)
+        .into_iter()
+        .map(|row| row.into_iter().map(|(c, _)| c).collect())
+        .collect()
+}
+
+/// Input boxes for the API keys and the Cancel / Submit buttons
+fn draw_settings<B: Backend>(f: &mut Frame<B>, area: Rect, brave: &str, openai: &str, active: usize, note: Option<&str>, theme: &Theme) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .split(area);
+    let width = 34.min(area.width);
+    let field = |row: Rect| Rect { x: row.x + (row.width - width) / 2, width, ..row };
+    let focus = |n: usize| {
+        if active == n {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().add_modifier(Modifier::DIM)
+        }
+    };
+
+    for (row, label, value, n) in [(rows[1], "Brave API Key:", brave, 0), (rows[3], "OpenAI API Key:", openai, 1)] {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(Span::styled(label, focus(n).fg(theme.title)));
+        f.render_widget(Paragraph::new(qvault_log::mask_secret(value)).block(block), field(row));
+    }
+
+    let buttons = Spans::from(vec![
+        Span::styled("[ Cancel ]", focus(2).fg(theme.warn)),
+        Span::raw("        "),
+        Span::styled("[ Submit ]", focus(3).fg(theme.accent)),
+    ]);
+    f.render_widget(Paragraph::new(buttons).alignment(Alignment::Center), rows[5]);
+    if let Some(note) = note {
+        f.render_widget(Paragraph::new(note).alignment(Alignment::Center).wrap(Wrap { trim: true }), rows[7]);
+    }
+}
+
+/// Log lines colored by level, between a header and a key help footer
+fn draw_log<B: Backend>(f: &mut Frame<B>, area: Rect, title: &str, lines: &[String], scroll: usize, footer: &str, theme: &Theme) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)])
+        .split(area);
+    let page = rows[1].height as usize;
+    let start = scroll.min(lines.len().saturating_sub(page));
+    let header = format!("{}  {}/{}", title, (start + page).min(lines.len()), lines.len());
+    f.render_widget(Paragraph::new(Span::styled(header, Style::default().add_modifier(Modifier::BOLD))), rows[0]);
+    let text: Vec<Spans> = lines
+        .iter()
+        .skip(start)
+        .take(page)
+        .map(|line| {
+            let style = match qvault_log::line_level(line) {
+                Some(Level::Error) => Style::default().fg(theme.error),
+                Some(Level::Warn) => Style::default().fg(theme.warn),
+                Some(Level::Debug) | Some(Level::Trace) => Style::default().add_modifier(Modifier::DIM),
+                _ => Style::default(),
+            };
+            Spans::from(Span::styled(line.as_str(), style))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(text), rows[1]);
+    f.render_widget(Paragraph::new(Span::styled(footer, Style::default().add_modifier(Modifier::DIM))), rows[2]);
+}
+
+/// Longest prefix of `text` that fits in `width` columns
+fn clip_to_width(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        let mut next = out.clone();
+        next.push(c);
+        if Span::raw(next.as_str()).width() > width {
+            break;
+        }
+        out = next;
+    }
+    out
+}
+
+/// Append complete lines written after `offset`, returning the new offset.
+/// A trailing partial line is left for the next call.
+fn read_new_lines(file: &mut File, offset: u64, lines: &mut Vec<String>) -> io::Result<u64> {
+    let len = file.metadata()?.len();
+    // Log was truncated or rotated underneath us, start over
+    let offset = if len < offset {
+        lines.clear();
+        0
+    } else {
+        offset
+    };
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut reader = BufReader::new(file);
+    let mut consumed = offset;
+    let mut buf = String::new();
+    loop {
+        buf.clear();
+        let n = BufRead::read_line(&mut reader, &mut buf)?;
+        if n == 0 || !buf.ends_with('\n') {
+            break;
+        }
+        consumed += n as u64;
+        lines.push(buf.trim_end().to_string());
+    }
+    Ok(consumed)
+}
+
+/// Commands that start with what was typed, only while the first word is being typed
+fn complete_command(buffer: &str, completions: &[String]) -> Vec<String> {
+    if !buffer.starts_with('/') || buffer.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+    let typed = buffer.to_lowercase();
+    completions.iter().filter(|c| c.starts_with(&typed)).cloned().collect()
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let mut prefix = words.first().cloned().unwrap_or_default();
+    for w in words.iter().skip(1) {
+        while !w.starts_with(&prefix) {
+            prefix.pop();
+        }
+    }
+    prefix
+}
+
+/// Result list above the detail pane
+fn results_layout(area: Rect) -> Vec<Rect> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Min(5)])
+        .split(area)
+}
+
+/// Rows per result in the list: title, then domain and description
+const RESULT_ROWS: usize = 2;
+
+/// Index of the result drawn at column `x`, row `y` of the screen
+fn result_at(area: Rect, offset: usize, x: u16, y: u16) -> Option<usize> {
+    let list = results_layout(area)[0];
+    let inside = x > list.x && x + 1 < list.right() && y > list.y && y + 1 < list.bottom();
+    inside.then(|| offset + (y - list.y - 1) as usize / RESULT_ROWS)
+}
+
+/// Result list on top, details of the selected result below
+fn draw_results<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    query: &str,
+    result: &SearchResult,
+    offset: &mut usize,
+    theme: &Theme,
+    keymap: &Keymap,
+) {
+    let chunks = results_layout(area);
+
+    // Scroll the list just enough to keep the selected result in view
+    let fits = (chunks[0].height.saturating_sub(2) as usize / RESULT_ROWS).max(1);
+    if result.index() < *offset {
+        *offset = result.index();
+    } else if result.index() >= *offset + fits {
+        *offset = result.index() + 1 - fits;
+    }
+
+    let dim = Style::default().fg(theme.dim);
+    let items: Vec<ListItem> = result
+        .items()
+        .iter()
+        .enumerate()
+        .skip(*offset)
+        .map(|(i, item)| {
+            ListItem::new(vec![
+                Spans::from(vec![
+                    Span::styled(format!("{:>2}. ", i + 1), dim),
+                    Span::styled(item.title().to_string(), Style::default().add_modifier(Modifier::BOLD)),
+                ]),
+                Spans::from(vec![
+                    Span::raw("    "),
+                    Span::styled(item.domain().to_string(), Style::default().fg(theme.accent)),
+                    Span::styled(format!("  {}", item.description()), dim),
+                ]),
+            ])
+        })
+        .collect();
+
+    let title = format!(
+        " {} results for {}  {}/{} move  {} open  {}/{} copy  {} back ",
+        result.count(),
+        query,
+        keymap.hint(Action::Next),
+        keymap.hint(Action::Prev),
+        keymap.hint(Action::Open),
+        keymap.hint(Action::CopyUrl),
+        keymap.hint(Action::CopyMarkdown),
+        keymap.hint(Action::Quit)
+    );
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(if theme.color {
+            Style::default().bg(theme.highlight_bg).fg(theme.highlight_fg)
+        } else {
+            Style::default().add_modifier(Modifier::REVERSED)
+        });
+    let mut state = ListState::default();
+    state.select(Some(result.index() - *offset));
+    f.render_stateful_widget(list, chunks[0], &mut state);
+
+    let detail = vec![
+        Spans::from(Span::styled(result.title().to_string(), Style::default().add_modifier(Modifier::BOLD))),
+        Spans::from(Span::styled(
+            result.url().to_string(),
+            Style::default().fg(theme.url).add_modifier(Modifier::UNDERLINED),
+        )),
+        Spans::from(""),
+        Spans::from(result.snippet().to_string()),
+    ];
+    let pane = Paragraph::new(detail)
+        .block(Block::default().borders(Borders::ALL).title(format!(" {} / {} ", result.index() + 1, result.count())))
+        .wrap(Wrap { trim: true });
+    f.render_widget(pane, chunks[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qvault_cmd::{qvault_builtin, CommandRegistry};
+    use crate::qvault_config::QvaultConfig;
+    use tui::backend::TestBackend;
+
+    #[test]
+    fn test_draw_results_list_and_detail() {
+        let mut result = SearchResult::from_items(&[
+            ("Tokio select", "https://docs.rs/tokio/latest/tokio/macro.select.html", "Waits on multiple branches"),
+            ("Async book", "https://www.rust-lang.org/async", "Select and join"),
+        ]);
+        result.select(1);
+        let mut terminal = tui::Terminal::new(TestBackend::new(60, 20)).unwrap();
+        let mut offset = 0;
+        terminal
+            .draw(|f| draw_results(f, f.size(), "tokio select", &result, &mut offset, &Theme::default(), &Keymap::default()))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..20)
+            .map(|y| (0..60).map(|x| buffer.get(x, y).symbol.as_str()).collect())
+            .collect();
+        let screen = rows.join("\n");
+        assert!(screen.contains("2 results for tokio select"));
+        assert!(screen.contains(" 1. Tokio select"));
+        assert!(screen.contains("docs.rs  Waits on multiple branches"));
+        assert!(screen.contains("rust-lang.org  Select and join"));
+        assert!(screen.contains(" 2 / 2 "));
+        assert!(screen.contains("https://www.rust-lang.org/async"));
+        assert_eq!(offset, 0);
+        // Border row, then two rows per result
+        assert_eq!(result_at(Rect::new(0, 0, 60, 20), offset, 5, 1), Some(0));
+        assert_eq!(result_at(Rect::new(0, 0, 60, 20), offset, 5, 4), Some(1));
+        assert_eq!(result_at(Rect::new(0, 0, 60, 20), offset, 0, 4), None);
+    }
+
+    #[test]
+    fn test_tab_strip_lists_used_tabs() {
+        let mut screen = Screen {
+            pane: Pane::Text(TextPane { title: Some("AI Response".to_string()), ..TextPane::default() }),
+            status: Status::Info(String::new()),
+            prompt: String::new(),
+            typing: false,
+            caret: 0,
+            results_offset: 0,
+            tabs: (0..TAB_COUNT).map(|_| Tab::default()).collect(),
+            active: 1,
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+        };
+        screen.tabs[0].pane = Pane::Results {
+            query: "tokio select".to_string(),
+            result: SearchResult::from_items(&[("Tokio", "https://tokio.rs", "")]),
+        };
+        assert_eq!(tab_strip(&screen), [(1, "tokio select".to_string()), (2, "AI Response".to_string())]);
+
+        screen.active = 4;
+        screen.pane = Pane::Text(TextPane::default());
+        assert_eq!(tab_strip(&screen)[1], (5, "new".to_string()));
+        assert_eq!(tab_key(&Event::Key(Key::Alt('3'))), Some(3));
+        assert_eq!(tab_key(&Event::Key(Key::Alt('0'))), None);
+    }
+
+    #[test]
+    fn test_input_start_keeps_caret_in_view() {
+        assert_eq!(input_start("/search tokio", 13, 20), 0);
+        assert_eq!(input_start("/search tokio", 13, 5), 8);
+        assert_eq!(input_start("/search tokio", 3, 5), 0);
+        assert_eq!(input_start("日本語", 9, 4), 3);
+    }
+
+    #[test]
+    fn test_wrap_line() {
+        assert_eq!(wrap_line("the quick brown fox", 10), ["the quick", "brown fox"]);
+        assert_eq!(wrap_line("", 10), [""]);
+        assert_eq!(wrap_line("abcdefghij klm", 4), ["abcd", "efgh", "ij", "klm"]);
+        assert_eq!(wrap_line("    indented code", 12), ["    indented", "code"]);
+        // Wide characters take two columns each
+        assert_eq!(wrap_line("日本語のテキスト", 6), ["日本語", "のテキ", "スト"]);
+    }
+
+    #[test]
+    fn test_draw_text_pane_scrolls() {
+        let body: Vec<String> = (1..=30).map(|i| format!("line {}", i)).collect();
+        let pane = TextPane {
+            title: Some("ai".to_string()),
+            messages: vec![(1, body.join("\n"))],
+            scroll: 10,
+            ..TextPane::default()
+        };
+        let mut terminal = tui::Terminal::new(TestBackend::new(40, 12)).unwrap();
+        terminal
+            .draw(|f| {
+                draw_text_pane(f, f.size(), &pane, &Theme::default());
+            })
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let screen: String = (0..12)
+            .map(|y| (0..40).map(|x| buffer.get(x, y).symbol.as_str()).collect::<String>() + "\n")
+            .collect();
+        assert!(screen.contains("line 11"));
+        assert!(!screen.contains("line 10 "));
+        assert!(screen.contains("11-16/30 (53%)"));
+    }
+
+    #[test]
+    fn test_help_box_scrolls_to_the_keys() {
+        let help = qvault_builtin::overview(&CommandRegistry::with_builtins(), &QvaultConfig::default());
+        let mut pane = TextPane { title: Some("Help".to_string()), help: Some(help.clone()), ..TextPane::default() };
+        let mut terminal = tui::Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut draw = |pane: &TextPane| -> String {
+            terminal
+                .draw(|f| {
+                    draw_text_pane(f, screen_layout(f.size())[1], pane, &Theme::default());
+                })
+                .unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..24)
+                .map(|y| (0..80).map(|x| buffer.get(x, y).symbol.as_str()).collect::<String>() + "\n")
+                .collect()
+        };
+
+        // The command list alone fills an 80x24 screen
+        let screen = draw(&pane);
+        assert!(screen.contains("/help (/?) - Show this help message"));
+        assert!(!screen.contains("Keys (default):"));
+
+        let output = screen_layout(Rect::new(0, 0, 80, 24))[1];
+        let (rect, rows) = help_box(text_pane_layout(output, &pane).1, &help);
+        pane.scroll = rows.len() - message_page(rows.len(), rect.height as usize - 2);
+        let screen = draw(&pane);
+        assert!(screen.contains("Keys (default):"));
+        assert!(screen.contains("Alt-1..Alt-9 switch tabs"));
+        assert!(screen.contains("the left or right half of the bar to page"));
+        assert!(screen.contains(&format!("/{} (100%)", rows.len())));
     }
 }